@@ -39,6 +39,21 @@ pub struct GimmickAssets {
 
     #[asset(path = "gimmick/lock1.png")]
     pub lock1: Handle<Image>,
+
+    #[asset(path = "gimmick/hole.png")]
+    pub hole: Handle<Image>,
+
+    #[asset(path = "gimmick/teleporter.png")]
+    pub teleporter: Handle<Image>,
+
+    #[asset(path = "gimmick/portal.png")]
+    pub portal: Handle<Image>,
+
+    #[asset(path = "gimmick/arrow.png")]
+    pub arrow: Handle<Image>,
+
+    #[asset(path = "gimmick/gate.png")]
+    pub gate: Handle<Image>,
 }
 
 
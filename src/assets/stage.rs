@@ -1,50 +1,154 @@
-use bevy::asset::{Assets, Handle};
-use bevy::prelude::{AssetServer, Resource};
+use bevy::asset::{Assets, Handle, HandleUntyped};
+use bevy::prelude::{AssetServer, Resource, World};
 use bevy::reflect::TypeUuid;
-use bevy_asset_loader::prelude::AssetCollection;
+use bevy_asset_loader::prelude::{AssetCollection, DynamicAsset, DynamicAssetCollection, DynamicAssets, DynamicAssetType};
 use bevy_inspector_egui::__macro_exports::bevy_reflect::TypePath;
+use serde::Deserialize;
 
 use crate::loader::json::StageJson;
+use crate::progress::{Progress, StageId};
 
-macro_rules! stage_assets {
-    ($($stage: ident, $path: expr),*) => {
-        #[derive(AssetCollection, Resource, Default, Debug)]
-        pub struct StageAssets {
-            $(
-                #[asset(path = $path)]
-                pub $stage: Handle<StageJson>,
-            )*
-        }
+pub const CATALOGUE_PATH: &str = "stages/built_in.catalogue.json";
 
+const STAGES_KEY: &str = "stages";
 
-        impl StageAssets{
-            pub fn stages(&self, asset: &mut Assets<StageJson>) -> Vec<StageJson>{
-                vec![
-                    $(self.$stage.clone()),*
-                ]
-                    .iter()
-                    .map(|s|asset.get(s).unwrap().clone())
-                    .collect()
-            }
-        }
-    };
+
+/// Lists the built-in stages; adding an entry here is enough to ship a new stage.
+#[derive(Deserialize, TypePath, TypeUuid, Debug, Clone, PartialEq)]
+#[uuid = "0b6a3e9c-52f4-4d0e-9a4f-7c1d2e8b5a61"]
+pub struct StageCatalogue {
+    pub packs: Vec<CataloguePack>,
+    pub stages: Vec<CatalogueStage>,
+}
+
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CataloguePack {
+    pub id: String,
+    pub name: String,
+    /// Stage ids in play order.
+    pub stages: Vec<String>,
+    #[serde(default)]
+    pub unlock: UnlockRule,
+}
+
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnlockRule {
+    #[default]
+    Always,
+
+    /// Clear this many stages of the pack before it.
+    ClearPrevious(usize),
+
+    /// Clear this many built-in stages in any pack.
+    ClearTotal(usize),
+}
+
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CatalogueStage {
+    pub id: String,
+    pub name: String,
+    /// Relative to the catalogue's directory.
+    pub file: String,
+}
+
+
+impl CatalogueStage {
+    #[inline]
+    pub fn asset_path(&self) -> String {
+        format!("stages/{}", self.file)
+    }
 }
 
 
-stage_assets!(
-    stage1, "stages/stage1.stage.json",
-    stage2, "stages/stage2.stage.json",
-    stage3, "stages/stage3.stage.json",
-    stage4, "stages/stage4.stage.json",
-    stage5, "stages/stage5.stage.json",
-    stage6, "stages/stage6.stage.json",
-    stage7, "stages/stage7.stage.json",
-    stage8, "stages/stage8.stage.json",
-    stage9, "stages/stage9.stage.json"
-);
+impl StageCatalogue {
+    /// Stages in play order with the index of their pack; ids missing from `stages` are skipped.
+    pub fn pack_stages(&self) -> Vec<(usize, &CatalogueStage)> {
+        self
+            .packs
+            .iter()
+            .enumerate()
+            .flat_map(|(pack_index, pack)| pack
+                .stages
+                .iter()
+                .filter_map(|id| self.stages.iter().find(|stage| &stage.id == id))
+                .map(move |stage| (pack_index, stage)))
+            .collect()
+    }
+}
 
 
+impl DynamicAssetCollection for StageCatalogue {
+    fn register(&self, dynamic_assets: &mut DynamicAssets) {
+        dynamic_assets.register_asset(STAGES_KEY, Box::new(CatalogueFiles(self
+            .pack_stages()
+            .iter()
+            .map(|(_, stage)| stage.asset_path())
+            .collect())));
+    }
+}
+
 
+#[derive(Debug)]
+struct CatalogueFiles(Vec<String>);
+
+
+impl DynamicAsset for CatalogueFiles {
+    fn load(&self, asset_server: &AssetServer) -> Vec<HandleUntyped> {
+        self
+            .0
+            .iter()
+            .map(|path| asset_server.load_untyped(path))
+            .collect()
+    }
+
+
+    fn build(&self, world: &mut World) -> Result<DynamicAssetType, anyhow::Error> {
+        let asset_server = world.resource::<AssetServer>();
+        Ok(DynamicAssetType::Collection(self.load(asset_server)))
+    }
+}
+
+
+#[derive(AssetCollection, Resource, Debug)]
+pub struct StageAssets {
+    #[asset(path = "stages/built_in.catalogue.json")]
+    pub catalogue: Handle<StageCatalogue>,
+
+    /// In the order of [`StageCatalogue::pack_stages`].
+    #[asset(key = "stages", collection(typed))]
+    pub stages: Vec<Handle<StageJson>>,
+}
+
+
+impl StageAssets {
+    pub fn built_in_stages(&self, catalogues: &Assets<StageCatalogue>, stages: &Assets<StageJson>) -> BuiltInStages {
+        let catalogue = catalogues.get(&self.catalogue).unwrap();
+        let mut packs = catalogue
+            .packs
+            .iter()
+            .map(|pack| StagePack {
+                id: pack.id.clone(),
+                name: pack.name.clone(),
+                unlock: pack.unlock,
+                stages: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        for ((pack_index, entry), handle) in catalogue.pack_stages().into_iter().zip(self.stages.iter()) {
+            packs[pack_index].stages.push(BuiltInStage {
+                id: entry.id.clone(),
+                name: entry.name.clone(),
+                stage: stages.get(handle).unwrap().clone(),
+            });
+        }
+
+        BuiltInStages(packs)
+    }
+}
 
 
 #[derive(Resource, TypePath, TypeUuid, Debug)]
@@ -52,5 +156,179 @@ stage_assets!(
 pub struct StageHandle(pub Handle<StageJson>);
 
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuiltInStage {
+    pub id: String,
+    pub name: String,
+    pub stage: StageJson,
+}
+
+
+impl BuiltInStage {
+    #[inline]
+    pub fn stage_id(&self) -> StageId {
+        StageId::built_in(&self.id)
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagePack {
+    pub id: String,
+    pub name: String,
+    pub unlock: UnlockRule,
+    pub stages: Vec<BuiltInStage>,
+}
+
+
+impl StagePack {
+    #[inline]
+    pub fn cleared_count(&self, progress: &Progress) -> usize {
+        self
+            .stages
+            .iter()
+            .filter(|stage| progress.is_cleared(&stage.stage_id()))
+            .count()
+    }
+}
+
+
+/// Packs in catalogue order.
 #[derive(Resource, Debug, Clone, PartialEq)]
-pub struct BuiltInStages(pub Vec<StageJson>);
+pub struct BuiltInStages(pub Vec<StagePack>);
+
+
+impl BuiltInStages {
+    pub fn is_unlocked(&self, pack_index: usize, progress: &Progress) -> bool {
+        match self.0[pack_index].unlock {
+            UnlockRule::Always => true,
+            UnlockRule::ClearPrevious(count) => pack_index
+                .checked_sub(1)
+                .map_or(true, |previous| count <= self.0[previous].cleared_count(progress)),
+            UnlockRule::ClearTotal(count) => count <= self
+                .0
+                .iter()
+                .map(|pack| pack.cleared_count(progress))
+                .sum(),
+        }
+    }
+
+
+    /// The stage after `id` in play order, as long as its pack is unlocked.
+    pub fn next_stage(&self, id: &StageId, progress: &Progress) -> Option<&BuiltInStage> {
+        let (pack_index, stage_index) = self
+            .0
+            .iter()
+            .enumerate()
+            .find_map(|(pack_index, pack)| pack
+                .stages
+                .iter()
+                .position(|stage| stage.stage_id() == *id)
+                .map(|stage_index| (pack_index, stage_index)))?;
+
+        self
+            .0
+            .iter()
+            .enumerate()
+            .skip(pack_index)
+            .filter(|(index, _)| self.is_unlocked(*index, progress))
+            .flat_map(|(index, pack)| pack
+                .stages
+                .iter()
+                .skip(if index == pack_index { stage_index + 1 } else { 0 }))
+            .next()
+    }
+
+
+    /// What the player still has to do to open the pack.
+    pub fn requirement(&self, pack_index: usize) -> String {
+        match self.0[pack_index].unlock {
+            UnlockRule::Always => String::new(),
+            UnlockRule::ClearPrevious(count) => match pack_index.checked_sub(1) {
+                Some(previous) => format!("Clear {count} in {}", self.0[previous].name),
+                None => String::new()
+            },
+            UnlockRule::ClearTotal(count) => format!("Clear {count} stages"),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::assets::stage::{BuiltInStage, BuiltInStages, CATALOGUE_PATH, StageCatalogue, StagePack, UnlockRule};
+    use crate::loader::json::StageJson;
+    use crate::progress::{Progress, StageId, StageResult};
+    use crate::simulation::tests::stage;
+
+    fn catalogue() -> StageCatalogue {
+        serde_json::from_str(&fs::read_to_string(Path::new("assets").join(CATALOGUE_PATH)).unwrap()).unwrap()
+    }
+
+
+    #[test]
+    fn every_catalogue_stage_exists() {
+        for stage in catalogue().stages {
+            assert!(Path::new("assets").join(stage.asset_path()).exists(), "{}", stage.id);
+        }
+    }
+
+
+    #[test]
+    fn pack_stages_in_play_order() {
+        let catalogue = catalogue();
+        let ids = catalogue.pack_stages().into_iter().map(|(_, stage)| stage.id.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(ids, (1..=9).map(|i| format!("stage{i}")).collect::<Vec<_>>());
+    }
+
+
+    #[test]
+    fn unlock_after_clearing_previous_pack() {
+        let stages = BuiltInStages(vec![pack("first", UnlockRule::Always, &["a", "b"]), pack("second", UnlockRule::ClearPrevious(2), &["c"])]);
+        let mut progress = Progress::default();
+        progress.add_clear(&stages.0[0].stages[0].stage_id(), StageResult::default());
+        assert!(!stages.is_unlocked(1, &progress));
+
+        progress.add_clear(&stages.0[0].stages[1].stage_id(), StageResult::default());
+        assert!(stages.is_unlocked(1, &progress));
+    }
+
+
+    #[test]
+    fn next_stage_crosses_unlocked_packs() {
+        let stages = BuiltInStages(vec![pack("first", UnlockRule::Always, &["a", "b"]), pack("second", UnlockRule::ClearPrevious(2), &["c"])]);
+        let mut progress = Progress::default();
+        progress.add_clear(&stages.0[0].stages[0].stage_id(), StageResult::default());
+
+        assert_eq!(stages.next_stage(&StageId::built_in("a"), &progress).map(|stage| stage.id.as_str()), Some("b"));
+        assert_eq!(stages.next_stage(&StageId::built_in("b"), &progress), None);
+
+        progress.add_clear(&stages.0[0].stages[1].stage_id(), StageResult::default());
+        assert_eq!(stages.next_stage(&StageId::built_in("b"), &progress).map(|stage| stage.id.as_str()), Some("c"));
+        assert_eq!(stages.next_stage(&StageId::built_in("c"), &progress), None);
+    }
+
+
+    fn pack(name: &str, unlock: UnlockRule, stage_names: &[&str]) -> StagePack {
+        StagePack {
+            id: name.to_string(),
+            name: name.to_string(),
+            unlock,
+            stages: stage_names
+                .iter()
+                .map(|name| BuiltInStage {
+                    id: name.to_string(),
+                    name: name.to_string(),
+                    stage: StageJson {
+                        name: name.to_string(),
+                        ..stage(vec![])
+                    },
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::fs;
+use std::path::Path;
 
 use bevy::app::{App, Plugin, Update};
 use bevy::core::Name;
@@ -10,11 +11,13 @@ use bevy::ui::{AlignItems, BackgroundColor, Interaction, JustifyContent, Val};
 use crate::assets::font::FontAssets;
 use crate::{destroy_all, mouse_just_pressed_left};
 use crate::extension::InteractionCondition;
+use crate::error::GameResult;
 use crate::gama_state::GameState;
 use crate::loader::json::StageJson;
+use crate::loader::{migration, validation};
+use crate::loader::validation::MAX_PAGES;
 use crate::page::page_count::PageCount;
 use crate::stage_edit::stage_name::StageName;
-use crate::window::WindowParams;
 
 #[derive(Default, Debug, PartialEq, Copy, Clone)]
 pub struct BeforeStageEditPlugin;
@@ -51,6 +54,7 @@ fn setup(
         .with_children(|parent| {
             page_count_ui(parent, &font);
             start_button(parent, &font);
+            drop_error_text(parent, &font);
         });
 }
 
@@ -59,7 +63,6 @@ fn interaction(
     mut state: ResMut<NextState<GameState>>,
     mut commands: Commands,
     mut page_count: Query<&mut Text, With<PageCountText>>,
-    window_params: WindowParams,
     down: Query<&Interaction, (With<Interaction>, With<PageDownButton>)>,
     up: Query<&Interaction, (With<Interaction>, With<PageUpButton>)>,
     start_button: Query<&Interaction, (With<Interaction>, With<StartButton>)>,
@@ -67,14 +70,13 @@ fn interaction(
     let count = page_count.single().sections[0].value.parse::<usize>().unwrap();
     if down.single().pressed() && 0 < count {
         page_count.single_mut().sections[0].value = (count - 1).to_string();
-    } else if up.single().pressed() {
+    } else if up.single().pressed() && count < MAX_PAGES {
         page_count.single_mut().sections[0].value = (count + 1).to_string();
     } else if start_button.single().pressed() {
         commands.insert_resource(StageJson::empty_stage(
             PageCount::new(count),
             25,
             14,
-            window_params.top_left(),
         ));
 
         state.set(GameState::StageEdit);
@@ -86,19 +88,30 @@ fn stage_file_drop_system(
     mut state: ResMut<NextState<GameState>>,
     mut commands: Commands,
     mut evr: EventReader<FileDragAndDrop>,
+    mut error_text: Query<&mut Text, With<DropErrorText>>,
 ) {
     for ev in evr.iter() {
         if let FileDragAndDrop::DroppedFile { window: _, path_buf } = ev {
-            let json = fs::read_to_string(path_buf).unwrap();
-            let json = serde_json::from_str::<StageJson>(&json).unwrap();
-
-            commands.insert_resource(StageName(json.name.clone()));
-            commands.insert_resource(json);
-            state.set(GameState::StageEdit);
+            match read_dropped_stage(path_buf) {
+                Ok(json) => {
+                    commands.insert_resource(StageName(json.name.clone()));
+                    commands.insert_resource(json);
+                    state.set(GameState::StageEdit);
+                }
+                Err(error) => {
+                    error_text.single_mut().sections[0].value = error.to_string();
+                }
+            }
         }
     }
 }
 
+
+#[inline]
+fn read_dropped_stage(path: &Path) -> GameResult<StageJson> {
+    validation::checked(migration::from_json(&fs::read_to_string(path)?)?)
+}
+
 #[derive(Default, Debug, PartialEq, Copy, Clone, Component)]
 struct PageCountText;
 
@@ -185,4 +198,18 @@ fn start_button(parent: &mut ChildBuilder, font: &FontAssets) {
             }));
         })
     ;
-}
\ No newline at end of file
+}
+
+
+#[derive(Default, Debug, PartialEq, Copy, Clone, Component)]
+struct DropErrorText;
+
+
+fn drop_error_text(parent: &mut ChildBuilder, font: &FontAssets) {
+    parent.spawn(TextBundle::from_section("", TextStyle {
+        font: font.button_text.clone(),
+        font_size: 23.,
+        color: Color::ORANGE_RED,
+    }))
+        .insert((Name::new("DropErrorText"), DropErrorText));
+}
@@ -1,5 +1,9 @@
+use itertools::Itertools;
 use thiserror::Error;
 
+use crate::loader::{STAGE_DIR_ENV, STAGE_DIR_FLAG};
+use crate::loader::validation::StageIssue;
+
 pub type GameResult<T = ()> = Result<T, GameError>;
 
 
@@ -10,4 +14,52 @@ pub enum GameError {
 
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
-}
\ No newline at end of file
+
+    #[error("stage file version {0} is unknown")]
+    UnknownStageVersion(String),
+
+    #[error("version 1 position ({x}, {y}) is outside the grid")]
+    OutOfGrid {
+        x: f32,
+        y: f32,
+    },
+
+    #[error("stage file version {found} is newer than the supported version {supported}")]
+    FutureStageVersion {
+        found: u32,
+        supported: u32,
+    },
+
+    #[error("stage `{name}` is invalid: {}", .issues.iter().join(", "))]
+    InvalidStage {
+        name: String,
+        issues: Vec<StageIssue>,
+    },
+
+    #[error("the platform has no data directory; set {STAGE_DIR_ENV} or pass {STAGE_DIR_FLAG}")]
+    MissingDataDir,
+
+    #[error("there is no web storage to keep stages in")]
+    MissingWebStorage,
+
+    #[error("web storage failed: {0}")]
+    WebStorage(String),
+
+    #[error("stages here can't be changed")]
+    ReadOnlyStages,
+
+    #[error("stage `{0}` doesn't exist")]
+    StageNotFound(String),
+
+    #[error("stage `{0}` already exists")]
+    StageExists(String),
+
+    #[error("the replay's stage `{0}` was not found")]
+    ReplayStageNotFound(String),
+
+    #[error("stage `{0}` has changed since the replay was recorded")]
+    ReplayStageChanged(String),
+
+    #[error("stage `{0}` has no player")]
+    MissingPlayer(String),
+}
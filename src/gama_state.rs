@@ -14,6 +14,9 @@ pub enum GameState {
     StageSelect,
 
     Stage,
+
+    /// Passed through for a single frame so the stage is torn down and set up again.
+    StageRestart,
 }
 
 
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+type Op = Box<dyn Fn(&mut World) + Send + Sync>;
+
+#[derive(Resource, Default)]
+pub struct UndoStack {
+    groups: Vec<Vec<Op>>,
+    reserved: Vec<Op>,
+    pending: usize,
+}
+
+pub struct UndoPlugin;
+
+impl Plugin for UndoPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UndoStack>();
+        app.add_undo_event::<UndoCallbackEvent>();
+        app.add_systems(PreUpdate, run_undo);
+        app.add_systems(Update, run_callbacks);
+    }
+}
+
+fn run_undo(world: &mut World) {
+    loop {
+        let group = {
+            let mut stack = world.resource_mut::<UndoStack>();
+            if stack.pending == 0 { return; }
+            stack.pending -= 1;
+            stack.groups.pop()
+        };
+        if let Some(group) = group {
+            for op in group.iter() { op(world); }
+        }
+    }
+}
+
+fn run_callbacks(mut commands: Commands, mut er: EventReader<UndoCallbackEvent>) {
+    for e in er.iter() { (e.0)(&mut commands); }
+}
+
+pub trait AppUndoEx {
+    fn add_undo_event<E: Event + Clone>(&mut self) -> &mut App;
+}
+
+impl AppUndoEx for App {
+    fn add_undo_event<E: Event + Clone>(&mut self) -> &mut App {
+        self.add_event::<E>()
+    }
+}
+
+#[derive(SystemParam)]
+pub struct UndoScheduler<'w, E: Event + Clone> {
+    stack: ResMut<'w, UndoStack>,
+    #[system_param(ignore)]
+    _m: std::marker::PhantomData<E>,
+}
+
+impl<'w, E: Event + Clone> UndoScheduler<'w, E> {
+    pub fn reserve(&mut self, e: E) {
+        self.stack.reserved.push(Box::new(move |w: &mut World| w.send_event(e.clone())));
+    }
+    pub fn reserve_default(&mut self) where E: Default {
+        self.reserve(E::default());
+    }
+    pub fn register(&mut self, e: E) {
+        self.stack.groups.push(vec![Box::new(move |w: &mut World| w.send_event(e.clone()))]);
+    }
+    pub fn register_all_reserved(&mut self) {
+        let r = std::mem::take(&mut self.stack.reserved);
+        self.stack.groups.push(r);
+    }
+}
+
+#[derive(SystemParam)]
+pub struct UndoRequester<'w> {
+    stack: ResMut<'w, UndoStack>,
+}
+
+impl<'w> UndoRequester<'w> {
+    pub fn undo(&mut self) {
+        self.stack.pending += 1;
+    }
+}
+
+#[derive(Event, Clone)]
+pub struct UndoCallbackEvent(Arc<dyn Fn(&mut Commands) + Send + Sync>);
+
+impl UndoCallbackEvent {
+    pub fn new(f: impl Fn(&mut Commands) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+}
+
+pub mod prelude {
+    pub use crate::{AppUndoEx, UndoCallbackEvent, UndoRequester, UndoScheduler};
+}
@@ -1,30 +1,157 @@
+use std::path::{Path, PathBuf};
+
 use auto_delegate::{delegate, Delegate};
-use crate::error::GameResult;
+use bevy::prelude::Resource;
+use thiserror::Error;
+
+use crate::error::{GameError, GameResult};
+use crate::loader::embedded::EmbeddedStageLoader;
 use crate::loader::json::StageJson;
+use crate::loader::memory::MemoryStageLoader;
+#[cfg(not(target_arch = "wasm32"))]
 use crate::loader::native::NativeStageLoader;
+#[cfg(target_arch = "wasm32")]
+use crate::loader::web::WebStageLoader;
 
+pub mod embedded;
 pub mod json;
-mod native;
+pub mod memory;
+pub mod migration;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+pub mod validation;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+
+/// Overrides the directory user stages are loaded from and saved to.
+pub const STAGE_DIR_ENV: &str = "ESKATE_STAGE_DIR";
+
+/// Same as [`STAGE_DIR_ENV`], given as `--stage-dir <path>` or `--stage-dir=<path>`; wins over the env var.
+pub const STAGE_DIR_FLAG: &str = "--stage-dir";
+
+
+/// Where the player's own stages are kept on this platform.
+#[cfg(not(target_arch = "wasm32"))]
+pub type UserStageLoader = NativeStageLoader;
+
+#[cfg(target_arch = "wasm32")]
+pub type UserStageLoader = WebStageLoader;
+
+
+pub type StageLoadResult = Result<StageJson, StageLoadError>;
+
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct StageMetadata {
+    /// The name stored inside the stage, which is what the other methods take.
+    pub name: String,
+    pub read_only: bool,
+}
 
 
 #[delegate]
 pub trait StageLoadable {
-    fn load(&self) -> GameResult<Vec<StageJson>>;
+    /// Lists the stored stages without validating them.
+    fn list(&self) -> GameResult<Vec<StageMetadata>>;
+
+
+    /// Fails only when the stages can't be listed at all; a broken file is reported in its own entry.
+    fn load(&self) -> GameResult<Vec<StageLoadResult>>;
 
 
     fn save(&self, json: &StageJson) -> GameResult;
+
+
+    fn delete(&self, name: &str) -> GameResult;
+
+
+    /// Renames the stage, including the name stored inside it.
+    fn rename(&self, from: &str, to: &str) -> GameResult;
 }
 
 
-#[derive(Delegate, Debug, Copy, Clone)]
+#[derive(Delegate, Debug, Clone, Resource)]
 #[to(StageLoadable)]
 pub enum StageLoader {
-    Native(NativeStageLoader)
+    User(UserStageLoader),
+    Embedded(EmbeddedStageLoader),
+    InMemory(MemoryStageLoader),
 }
 
 
 impl StageLoader {
+    /// Stages saved from the editor, kept in the platform data directory or web storage.
+    /// Where neither is available they are only kept until the game closes.
     pub fn new() -> Self {
-        Self::Native(NativeStageLoader)
+        UserStageLoader::user().map_or_else(|_| Self::InMemory(MemoryStageLoader::default()), Self::User)
+    }
+
+
+    /// Stages shipped with the game.
+    #[inline]
+    pub fn built_in() -> Self {
+        Self::Embedded(EmbeddedStageLoader::default())
     }
-}
\ No newline at end of file
+}
+
+
+#[derive(Debug, Error)]
+#[error("{}{}: {error}", .path.display(), .position.map(|(line, column)| format!(":{line}:{column}")).unwrap_or_default())]
+pub struct StageLoadError {
+    pub path: PathBuf,
+    /// Line and column of a syntax error, both starting at 1.
+    pub position: Option<(usize, usize)>,
+    pub error: GameError,
+}
+
+
+impl StageLoadError {
+    pub fn new(path: impl AsRef<Path>, error: GameError) -> Self {
+        let position = match &error {
+            GameError::SerdeJson(e) if e.line() != 0 => Some((e.line(), e.column())),
+            _ => None
+        };
+
+        Self {
+            path: path.as_ref().to_path_buf(),
+            position,
+            error,
+        }
+    }
+
+
+    #[inline]
+    pub fn file_name(&self) -> String {
+        self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::{migration, StageLoadError};
+
+    #[test]
+    fn syntax_error_has_position() {
+        let error = migration::from_json("{\"version\":3,\n\"name\":}").unwrap_err();
+        let error = StageLoadError::new("broken.stage.json", error);
+
+        assert_eq!(error.position, Some((2, 8)));
+        assert!(error.to_string().starts_with("broken.stage.json:2:8: "));
+    }
+
+
+    #[test]
+    fn invalid_stage_has_no_position() {
+        let error = migration::from_json("{\"version\":99,\"name\":\"a\",\"pages\":[]}").unwrap_err();
+        let error = StageLoadError::new("future.stage.json", error);
+
+        assert_eq!(error.position, None);
+        assert!(error.to_string().starts_with("future.stage.json: "));
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::assets::stage::{CATALOGUE_PATH, StageCatalogue};
+use crate::error::{GameError, GameResult};
+use crate::loader::json::StageJson;
+use crate::loader::{migration, StageLoadable, StageLoadError, StageLoadResult, StageMetadata, validation};
+
+/// The stages the game ships with, in play order, read through the catalogue in the assets directory.
+/// They can't be changed from the game.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct EmbeddedStageLoader {
+    assets: PathBuf,
+}
+
+
+impl EmbeddedStageLoader {
+    pub fn new(assets: impl Into<PathBuf>) -> Self {
+        Self {
+            assets: assets.into()
+        }
+    }
+
+
+    fn stage_paths(&self) -> GameResult<Vec<PathBuf>> {
+        let catalogue = serde_json::from_str::<StageCatalogue>(&fs::read_to_string(self.assets.join(CATALOGUE_PATH))?)?;
+
+        Ok(catalogue
+            .pack_stages()
+            .into_iter()
+            .map(|(_, stage)| self.assets.join(stage.asset_path()))
+            .collect())
+    }
+}
+
+
+impl Default for EmbeddedStageLoader {
+    #[inline]
+    fn default() -> Self {
+        Self::new("assets")
+    }
+}
+
+
+impl StageLoadable for EmbeddedStageLoader {
+    fn list(&self) -> GameResult<Vec<StageMetadata>> {
+        self
+            .stage_paths()?
+            .iter()
+            .map(|path| Ok(StageMetadata {
+                name: migration::stage_name(&fs::read_to_string(path)?)?,
+                read_only: true,
+            }))
+            .collect()
+    }
+
+
+    fn load(&self) -> GameResult<Vec<StageLoadResult>> {
+        Ok(self
+            .stage_paths()?
+            .into_iter()
+            .map(|path| read_stage(&path).map_err(|e| StageLoadError::new(path, e)))
+            .collect())
+    }
+
+
+    fn save(&self, _: &StageJson) -> GameResult {
+        Err(GameError::ReadOnlyStages)
+    }
+
+
+    fn delete(&self, _: &str) -> GameResult {
+        Err(GameError::ReadOnlyStages)
+    }
+
+
+    fn rename(&self, _: &str, _: &str) -> GameResult {
+        Err(GameError::ReadOnlyStages)
+    }
+}
+
+
+fn read_stage(path: &Path) -> GameResult<StageJson> {
+    validation::checked(migration::from_json(&fs::read_to_string(path)?)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::error::GameError;
+    use crate::loader::StageLoadable;
+    use crate::loader::embedded::EmbeddedStageLoader;
+
+    #[test]
+    fn load_every_stage() {
+        let stages = EmbeddedStageLoader::default().load().unwrap();
+
+        assert_eq!(stages.len(), EmbeddedStageLoader::default().list().unwrap().len());
+        assert!(!stages.is_empty());
+        assert!(stages.iter().all(Result::is_ok));
+    }
+
+
+    #[test]
+    fn read_only() {
+        let loader = EmbeddedStageLoader::default();
+
+        assert!(loader.list().unwrap().iter().all(|stage| stage.read_only));
+        assert!(matches!(loader.delete("stage1"), Err(GameError::ReadOnlyStages)));
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use bevy::math::Vec2;
 use bevy::prelude::{Component, Resource};
 use bevy::reflect::{TypePath, TypeUuid};
@@ -6,12 +8,30 @@ use bevy::reflect::erased_serde::__private::serde::{Deserialize, Serialize};
 use crate::page::page_count::PageCount;
 use crate::stage::playing::gimmick::{GIMMICK_HEIGHT, GIMMICK_WIDTH};
 use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::move_direction::MoveDirection;
+use crate::window::WINDOW_TOP_LEFT;
+
+/// Bumped whenever the format changes, together with a step in `loader::migration`.
+pub const STAGE_VERSION: u32 = 3;
+
+pub const ITEM_AREA_WIDTH: f32 = GIMMICK_WIDTH * 1.3;
+
+/// Vertical space between the items stacked in an item area.
+pub const ITEM_GAP: f32 = 8.;
+
+/// Hints available per attempt when the stage does not set its own budget.
+pub const DEFAULT_HINTS: usize = 3;
+
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Resource, TypePath, TypeUuid, Component)]
 #[uuid = "413be529-bfeb-41b3-9db0-4b8b380a2c43"]
 pub struct StageJson {
+    pub version: u32,
     pub name: String,
     pub pages: Vec<PageJson>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hints: Option<usize>,
 }
 
 
@@ -21,22 +41,32 @@ impl StageJson {
         page_count: PageCount,
         page_width: u8,
         page_height: u8,
-        top_left: Vec2,
     ) -> Self {
         Self {
+            version: STAGE_VERSION,
             name: "".to_string(),
-            pages: (0..*page_count).map(|page_index| empty_page(page_width, page_height, top_left, page_index, *page_count)).collect(),
+            pages: (0..*page_count).map(|_| empty_page(page_width, page_height)).collect(),
+            hints: None,
         }
     }
+
+
+    #[inline]
+    pub fn hint_budget(&self) -> usize {
+        self.hints.unwrap_or(DEFAULT_HINTS)
+    }
+
+
+    #[inline]
+    pub fn page_origin(&self, page_index: usize) -> Vec2 {
+        self.pages[page_index].origin(page_index, self.pages.len())
+    }
 }
 
 
 fn empty_page(
-    page_width: u8,
-    page_height: u8,
-    top_left: Vec2,
-    page_index: usize,
-    page_count: usize,
+    page_width: u16,
+    page_height: u16,
 ) -> PageJson {
     let mut cells = Vec::with_capacity(page_width as usize * page_height as usize);
     let w = page_width - 1;
@@ -50,29 +80,20 @@ fn empty_page(
                 GimmickTag::Floor
             };
 
-            let x = f32::from(x) * GIMMICK_WIDTH;
-            let y = -f32::from(y) * GIMMICK_HEIGHT;
-            cells.push(StageCell::new(Vec2::new(x, y), vec![tag]));
+            cells.push(StageCell::new(x, y, vec![tag]));
         }
     }
-    let item_area = ItemAreaJson {
-        width: GIMMICK_WIDTH * 1.3,
-        height: f32::from(page_height - 1) * GIMMICK_HEIGHT,
-        tags: vec![],
-    };
-    let d = diff(page_width, page_height, top_left, page_index, page_count);
+
     PageJson {
-        x: d.x - GIMMICK_WIDTH * 1.3,
-        y: d.y,
         cells,
-        item_area,
+        items: vec![],
     }
 }
 
 
 fn diff(
-    page_width: u8,
-    page_height: u8,
+    page_width: u16,
+    page_height: u16,
     top_left: Vec2,
     page_index: usize,
     page_count: usize,
@@ -91,45 +112,205 @@ fn diff(
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PageJson {
-    pub x: f32,
-    pub y: f32,
     pub cells: Vec<StageCell>,
-    pub item_area: ItemAreaJson,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<ItemCell>,
 }
 
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct ItemAreaJson {
-    pub width: f32,
-    pub height: f32,
-    pub tags: Vec<ItemCell>,
+impl PageJson {
+    /// The number of columns.
+    #[inline]
+    pub fn width(&self) -> u16 {
+        self.cells.iter().map(|cell| u16::from(cell.col) + 1).max().unwrap_or_default()
+    }
+
+
+    /// The number of rows.
+    #[inline]
+    pub fn height(&self) -> u16 {
+        self.cells.iter().map(|cell| u16::from(cell.row) + 1).max().unwrap_or_default()
+    }
+
+
+    #[inline]
+    pub fn item_area_size(&self) -> Vec2 {
+        Vec2::new(ITEM_AREA_WIDTH, f32::from(self.height().saturating_sub(1)) * GIMMICK_HEIGHT)
+    }
+
+
+    /// Top left of the page, item area included, laid out for the fixed window size.
+    #[inline]
+    pub fn origin(&self, page_index: usize, page_count: usize) -> Vec2 {
+        let d = diff(self.width(), self.height(), WINDOW_TOP_LEFT, page_index, page_count);
+        Vec2::new(d.x - ITEM_AREA_WIDTH, d.y)
+    }
 }
 
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ItemCell {
-    pub x: f32,
-    pub y: f32,
+    /// Slot in the item area, counted from the top.
+    pub row: u8,
     pub tag: GimmickTag,
+
+    #[serde(default, skip_serializing_if = "GimmickProperties::is_empty")]
+    pub properties: GimmickProperties,
+}
+
+
+impl ItemCell {
+    #[inline]
+    pub fn pixel(&self) -> Vec2 {
+        Vec2::new(0., -GIMMICK_HEIGHT - f32::from(self.row) * (GIMMICK_HEIGHT + ITEM_GAP))
+    }
+
+
+    #[inline]
+    pub fn row_from_pixel(y: f32) -> u8 {
+        ((-y - GIMMICK_HEIGHT) / (GIMMICK_HEIGHT + ITEM_GAP)).round().max(0.) as u8
+    }
 }
 
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct StageCell {
-    pub x: f32,
-    pub y: f32,
+    pub col: u8,
+    pub row: u8,
     pub tags: Vec<GimmickTag>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<GimmickTag, GimmickProperties>,
 }
 
 
 impl StageCell {
     #[inline]
-    pub const fn new(pos: Vec2, tags: Vec<GimmickTag>) -> Self {
+    pub const fn new(col: u8, row: u8, tags: Vec<GimmickTag>) -> Self {
         Self {
-            x: pos.x,
-            y: pos.y,
+            col,
+            row,
             tags,
+            properties: BTreeMap::new(),
         }
     }
+
+
+    /// The cell under `pos`, relative to the top left of the page's field.
+    #[inline]
+    pub fn from_pixel(pos: Vec2, tags: Vec<GimmickTag>) -> Self {
+        Self::new((pos.x / GIMMICK_WIDTH).round() as u8, (-pos.y / GIMMICK_HEIGHT).round() as u8, tags)
+    }
+
+
+    #[inline]
+    pub fn pixel(&self) -> Vec2 {
+        Vec2::new(f32::from(self.col) * GIMMICK_WIDTH, -f32::from(self.row) * GIMMICK_HEIGHT)
+    }
+
+
+    #[inline]
+    pub fn properties(&self, tag: GimmickTag) -> GimmickProperties {
+        self.properties.get(&tag).copied().unwrap_or_default()
+    }
+}
+
+
+/// Optional parameters of a single gimmick; anything left out falls back to the gimmick's default.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Component)]
+pub struct GimmickProperties {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<MoveDirection>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_keys: Option<usize>,
+
+    /// Colour of keys and locks, or the pair a teleporter belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<PortalTarget>,
+}
+
+
+impl GimmickProperties {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+
+/// The cell a portal sends the player to; it may be on any page.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PortalTarget {
+    pub page: usize,
+    pub col: u8,
+    pub row: u8,
 }
 
 
+impl PortalTarget {
+    #[inline]
+    pub fn cell(&self) -> StageCell {
+        StageCell::new(self.col, self.row, Vec::new())
+    }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use crate::loader::json::{GimmickProperties, ItemCell, StageCell, StageJson};
+    use crate::page::page_count::PageCount;
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+
+    #[test]
+    fn read_cell_without_properties() {
+        let cell = serde_json::from_str::<StageCell>(r#"{"col":1,"row":2,"tags":["Floor","Turn"]}"#).unwrap();
+        assert!(cell.properties.is_empty());
+        assert_eq!(cell.properties(GimmickTag::Turn), GimmickProperties::default());
+    }
+
+
+    #[test]
+    fn round_trip_properties() {
+        let json = r#"{"col":1,"row":2,"tags":["Floor","Turn"],"properties":{"Turn":{"rotation":"Left"}}}"#;
+        let cell = serde_json::from_str::<StageCell>(json).unwrap();
+        assert_eq!(cell.properties(GimmickTag::Turn).rotation, Some(MoveDirection::Left));
+        assert_eq!(serde_json::to_string(&cell).unwrap(), json);
+    }
+
+
+    #[test]
+    fn cell_pixel_round_trip() {
+        let cell = StageCell::from_pixel(Vec2::new(552., -96.), vec![GimmickTag::Floor]);
+        assert_eq!((cell.col, cell.row), (23, 4));
+        assert_eq!(cell.pixel(), Vec2::new(552., -96.));
+    }
+
+
+    #[test]
+    fn item_row_round_trip() {
+        let item = ItemCell { row: 2, tag: GimmickTag::Rock, properties: GimmickProperties::default() };
+        assert_eq!(ItemCell::row_from_pixel(item.pixel().y), 2);
+        assert_eq!(ItemCell::row_from_pixel(-24.), 0);
+    }
+
+
+    #[test]
+    fn place_pages_like_before() {
+        let stage = StageJson::empty_stage(PageCount::new(1), 25, 14);
+        assert!(stage.page_origin(0).abs_diff_eq(Vec2::new(-331.2, 168.), 0.01));
+
+        let stage = StageJson::empty_stage(PageCount::new(2), 25, 14);
+        assert!(stage.page_origin(0).abs_diff_eq(Vec2::new(-307.2, 376.), 0.01));
+        assert!(stage.page_origin(1).abs_diff_eq(Vec2::new(-307.2, 16.), 0.01));
+    }
+}
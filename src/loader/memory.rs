@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{GameError, GameResult};
+use crate::loader::json::StageJson;
+use crate::loader::{StageLoadable, StageLoadError, StageLoadResult, StageMetadata, validation};
+
+/// Keeps stages for as long as the loader lives; clones share the same stages.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStageLoader(Arc<Mutex<BTreeMap<String, StageJson>>>);
+
+
+impl StageLoadable for MemoryStageLoader {
+    fn list(&self) -> GameResult<Vec<StageMetadata>> {
+        Ok(self
+            .0
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|name| StageMetadata {
+                name: name.clone(),
+                read_only: false,
+            })
+            .collect())
+    }
+
+
+    fn load(&self) -> GameResult<Vec<StageLoadResult>> {
+        Ok(self
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stage)| validation::checked(stage.clone()).map_err(|e| StageLoadError::new(name, e)))
+            .collect())
+    }
+
+
+    fn save(&self, json: &StageJson) -> GameResult {
+        self.0.lock().unwrap().insert(json.name.clone(), json.clone());
+        Ok(())
+    }
+
+
+    fn delete(&self, name: &str) -> GameResult {
+        self
+            .0
+            .lock()
+            .unwrap()
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| GameError::StageNotFound(name.to_string()))
+    }
+
+
+    fn rename(&self, from: &str, to: &str) -> GameResult {
+        let mut stages = self.0.lock().unwrap();
+        if stages.contains_key(to) {
+            return Err(GameError::StageExists(to.to_string()));
+        }
+        let mut stage = stages
+            .remove(from)
+            .ok_or_else(|| GameError::StageNotFound(from.to_string()))?;
+        stage.name = to.to_string();
+        stages.insert(to.to_string(), stage);
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::error::GameError;
+    use crate::loader::memory::MemoryStageLoader;
+    use crate::loader::{StageLoadable, StageMetadata};
+    use crate::simulation::tests::{page, stage};
+
+    #[test]
+    fn save_rename_and_delete() {
+        let loader = MemoryStageLoader::default();
+        loader.save(&stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])])).unwrap();
+
+        loader.rename("test", "renamed").unwrap();
+        assert_eq!(loader.list().unwrap(), vec![StageMetadata { name: "renamed".to_string(), read_only: false }]);
+        assert_eq!(loader.load().unwrap()[0].as_ref().unwrap().name, "renamed");
+        assert!(matches!(loader.rename("test", "other"), Err(GameError::StageNotFound(_))));
+
+        loader.delete("renamed").unwrap();
+        assert!(loader.load().unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn clones_share_stages() {
+        let loader = MemoryStageLoader::default();
+        loader.clone().save(&stage(vec![])).unwrap();
+
+        assert_eq!(loader.list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::{GameError, GameResult};
+use crate::loader::json::{STAGE_VERSION, StageJson};
+
+mod v1;
+mod v2;
+
+type Migration = fn(Value) -> GameResult<Value>;
+
+/// `MIGRATIONS[n]` upgrades a file of version `n + 1` to version `n + 2`.
+const MIGRATIONS: [Migration; STAGE_VERSION as usize - 1] = [
+    v1::migrate,
+    v2::migrate,
+];
+
+
+/// Reads a stage file of any known version, upgrading it one version at a time.
+pub fn from_json(json: &str) -> GameResult<StageJson> {
+    let stage = serde_json::from_str::<Value>(json)?;
+    let version = version(&stage)?;
+    if version == STAGE_VERSION {
+        // Parse the text again so errors point at a line and column.
+        return Ok(serde_json::from_str::<StageJson>(json)?);
+    }
+
+    let stage = MIGRATIONS[version as usize - 1..]
+        .iter()
+        .try_fold(stage, |stage, migration| migration(stage))?;
+    Ok(serde_json::from_value::<StageJson>(stage)?)
+}
+
+
+#[derive(Deserialize)]
+struct NameOnly {
+    name: String,
+}
+
+
+/// Reads only the stage's name, which every version stores the same way.
+pub fn stage_name(json: &str) -> GameResult<String> {
+    Ok(serde_json::from_str::<NameOnly>(json)?.name)
+}
+
+
+fn version(stage: &Value) -> GameResult<u32> {
+    // Files saved before the version field was added.
+    let Some(version) = stage.get("version") else { return Ok(1); };
+
+    match version.as_u64() {
+        Some(found) if u64::from(STAGE_VERSION) < found => Err(GameError::FutureStageVersion {
+            found: u32::try_from(found).unwrap_or(u32::MAX),
+            supported: STAGE_VERSION,
+        }),
+        Some(found) if 1 <= found => Ok(found as u32),
+        _ => Err(GameError::UnknownStageVersion(version.to_string())),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::error::GameError;
+    use crate::loader::json::{STAGE_VERSION, StageCell, StageJson};
+    use crate::loader::migration::from_json;
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+
+    #[test]
+    fn migrate_pixel_stage() {
+        let json = r#"{"name":"old","pages":[{"x":-331.2,"y":168.0,"cells":[{"x":48.0,"y":-24.0,"tags":["Floor","Player"]}],"item_area":{"width":31.2,"height":312.0,"tags":[{"x":0.0,"y":-56.0,"tag":"Rock"}]}}]}"#;
+        let stage = from_json(json).unwrap();
+
+        assert_eq!(stage.version, STAGE_VERSION);
+        assert_eq!(stage.pages[0].cells[0], StageCell::new(2, 1, vec![GimmickTag::Floor, GimmickTag::Player]));
+        assert_eq!(stage.pages[0].items[0].row, 1);
+    }
+
+
+    #[test]
+    fn reject_pixel_stage_outside_grid() {
+        let json = r#"{"name":"old","pages":[{"cells":[{"x":-48.0,"y":-24.0,"tags":["Floor"]}],"item_area":{"tags":[]}}]}"#;
+        assert!(matches!(from_json(json), Err(GameError::OutOfGrid { x, y }) if x == -48. && y == -24.));
+
+        let json = r#"{"name":"old","pages":[{"cells":[{"x":24.0,"y":-7000.0,"tags":["Floor"]}],"item_area":{"tags":[]}}]}"#;
+        assert!(matches!(from_json(json), Err(GameError::OutOfGrid { .. })));
+    }
+
+
+    #[test]
+    fn migrate_version_2_stage() {
+        let json = r#"{"version":2,"name":"two","pages":[{"cells":[{"col":1,"row":1,"tags":["Floor","Player"]}],"items":[]}]}"#;
+        let stage = from_json(json).unwrap();
+
+        assert_eq!(stage.version, STAGE_VERSION);
+        assert_eq!(stage.pages[0].cells[0], StageCell::new(1, 1, vec![GimmickTag::Floor, GimmickTag::Player]));
+    }
+
+
+    #[test]
+    fn reject_future_version() {
+        let json = format!(r#"{{"version":{},"name":"new","pages":[]}}"#, STAGE_VERSION + 1);
+        assert!(matches!(from_json(&json), Err(GameError::FutureStageVersion { found, supported: STAGE_VERSION }) if found == STAGE_VERSION + 1));
+    }
+
+
+    #[test]
+    fn reject_unknown_version() {
+        assert!(matches!(from_json(r#"{"version":0,"name":"zero","pages":[]}"#), Err(GameError::UnknownStageVersion(_))));
+        assert!(matches!(from_json(r#"{"version":"2","name":"text","pages":[]}"#), Err(GameError::UnknownStageVersion(_))));
+    }
+
+
+    /// Built-in stages are read by the asset loader without migrations.
+    #[test]
+    fn built_in_stages_are_current() {
+        for entry in fs::read_dir("assets/stages").unwrap() {
+            let path = entry.unwrap().path();
+            if !path.to_string_lossy().ends_with(".stage.json") {
+                continue;
+            }
+            let json = fs::read_to_string(path).unwrap();
+            let stage = serde_json::from_str::<StageJson>(&json).unwrap();
+            assert_eq!(stage.version, STAGE_VERSION, "{}", stage.name);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use bevy::reflect::erased_serde::__private::serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{GameError, GameResult};
+
+/// Version 1 files were laid out with 24px cells and 8px between items.
+const CELL_SIZE: f32 = 24.;
+const ITEM_GAP: f32 = 8.;
+
+
+#[derive(Deserialize, Debug, Clone)]
+struct StageV1 {
+    name: String,
+    pages: Vec<PageV1>,
+}
+
+
+#[derive(Deserialize, Debug, Clone)]
+struct PageV1 {
+    cells: Vec<StageCellV1>,
+    item_area: ItemAreaV1,
+}
+
+
+#[derive(Deserialize, Debug, Clone)]
+struct ItemAreaV1 {
+    tags: Vec<ItemCellV1>,
+}
+
+
+#[derive(Deserialize, Debug, Clone)]
+struct ItemCellV1 {
+    y: f32,
+    tag: Value,
+    properties: Option<Value>,
+}
+
+
+#[derive(Deserialize, Debug, Clone)]
+struct StageCellV1 {
+    x: f32,
+    y: f32,
+    tags: Value,
+    properties: Option<Value>,
+}
+
+
+/// Pixel coordinates become columns and rows; page placement is no longer stored.
+pub fn migrate(stage: Value) -> GameResult<Value> {
+    let stage = serde_json::from_value::<StageV1>(stage)?;
+
+    Ok(json!({
+        "version": 2,
+        "name": stage.name,
+        "pages": stage.pages.into_iter().map(page).collect::<GameResult<Vec<Value>>>()?,
+    }))
+}
+
+
+fn page(page: PageV1) -> GameResult<Value> {
+    let cells = page
+        .cells
+        .into_iter()
+        .map(|cell| Ok(with_properties(json!({
+            "col": grid_index(cell.x / CELL_SIZE, cell.x, cell.y)?,
+            "row": grid_index(-cell.y / CELL_SIZE, cell.x, cell.y)?,
+            "tags": cell.tags,
+        }), cell.properties)))
+        .collect::<GameResult<Vec<Value>>>()?;
+
+    let items = page
+        .item_area
+        .tags
+        .into_iter()
+        .map(|item| Ok(with_properties(json!({
+            "row": grid_index(((-item.y - CELL_SIZE) / (CELL_SIZE + ITEM_GAP)).max(0.), 0., item.y)?,
+            "tag": item.tag,
+        }), item.properties)))
+        .collect::<GameResult<Vec<Value>>>()?;
+
+    Ok(json!({
+        "cells": cells,
+        "items": items,
+    }))
+}
+
+
+/// Rounds `index` to a column or row, which `as u8` would otherwise saturate silently.
+fn grid_index(index: f32, x: f32, y: f32) -> GameResult<u8> {
+    let index = index.round();
+    if (0. ..=f32::from(u8::MAX)).contains(&index) {
+        Ok(index as u8)
+    } else {
+        Err(GameError::OutOfGrid { x, y })
+    }
+}
+
+
+fn with_properties(mut cell: Value, properties: Option<Value>) -> Value {
+    if let (Value::Object(cell), Some(properties)) = (&mut cell, properties) {
+        cell.insert("properties".to_string(), properties);
+    }
+    cell
+}
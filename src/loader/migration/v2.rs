@@ -0,0 +1,12 @@
+use serde_json::{json, Value};
+
+use crate::error::GameResult;
+
+/// Version 3 added the Hole, Teleporter, Portal, Arrow and Gate tags and the `target` property.
+/// A version 2 file uses none of them, so only its version changes.
+pub fn migrate(mut stage: Value) -> GameResult<Value> {
+    if let Value::Object(stage) = &mut stage {
+        stage.insert("version".to_string(), json!(3));
+    }
+    Ok(stage)
+}
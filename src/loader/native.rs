@@ -1,57 +1,233 @@
+use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use bevy_trait_query::imports::Component;
+use directories::ProjectDirs;
 
-use crate::error::GameResult;
+use crate::error::{GameError, GameResult};
 use crate::loader::json::StageJson;
-use crate::loader::StageLoadable;
+use crate::loader::{migration, STAGE_DIR_ENV, STAGE_DIR_FLAG, StageLoadable, StageLoadError, StageLoadResult, StageMetadata, validation};
 
-#[derive(Debug, Copy, Clone, Component, Hash, Eq, PartialEq)]
-pub struct NativeStageLoader;
+const STAGE_EXTENSION: &str = ".stage.json";
+
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct NativeStageLoader {
+    dir: PathBuf,
+}
+
+
+impl NativeStageLoader {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into()
+        }
+    }
+
+
+    pub fn user() -> GameResult<Self> {
+        Ok(Self::new(user_stage_dir()?))
+    }
+
+
+    fn stage_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}{STAGE_EXTENSION}"))
+    }
+
+
+    fn stage_paths(&self) -> GameResult<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut paths = Vec::new();
+        for entry in self.dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+
+    /// Files don't have to be named after their stage, e.g. when copied in by hand.
+    fn find(&self, name: &str) -> GameResult<PathBuf> {
+        self
+            .stage_paths()?
+            .into_iter()
+            .find(|path| stage_name(path) == name)
+            .ok_or_else(|| GameError::StageNotFound(name.to_string()))
+    }
+}
 
 
 impl StageLoadable for NativeStageLoader {
-    fn load(&self) -> GameResult<Vec<StageJson>> {
-        let dir = eskate_dir_path()?;
-        Ok(dir
-            .read_dir()?
-            .map(|dir| dir.unwrap())
-            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "json"))
-            .map(|entry| fs::read_to_string(entry.path()).unwrap())
-            .map(|json| serde_json::from_str::<StageJson>(&json).unwrap())
+    fn list(&self) -> GameResult<Vec<StageMetadata>> {
+        Ok(self
+            .stage_paths()?
+            .iter()
+            .map(|path| StageMetadata {
+                name: stage_name(path),
+                read_only: fs::metadata(path).is_ok_and(|metadata| metadata.permissions().readonly()),
+            })
+            .collect())
+    }
+
+
+    fn load(&self) -> GameResult<Vec<StageLoadResult>> {
+        Ok(self
+            .stage_paths()?
+            .into_iter()
+            .map(|path| read_stage(&path).map_err(|e| StageLoadError::new(path, e)))
             .collect())
     }
 
 
     fn save(&self, json: &StageJson) -> GameResult {
-        let path = stage_path(&json.name)?;
-        fs::write(path, serde_json::to_string(json)?)?;
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.stage_path(&json.name), serde_json::to_string(json)?)?;
+        Ok(())
+    }
+
+
+    fn delete(&self, name: &str) -> GameResult {
+        fs::remove_file(self.find(name)?)?;
         Ok(())
     }
+
+
+    fn rename(&self, from: &str, to: &str) -> GameResult {
+        if self.find(to).is_ok() {
+            return Err(GameError::StageExists(to.to_string()));
+        }
+        let path = self.find(from)?;
+
+        let mut stage = migration::from_json(&fs::read_to_string(&path)?)?;
+        stage.name = to.to_string();
+        self.save(&stage)?;
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
+
+
+fn read_stage(path: &Path) -> GameResult<StageJson> {
+    validation::checked(migration::from_json(&fs::read_to_string(path)?)?)
 }
 
 
-fn eskate_dir_path() -> GameResult<PathBuf> {
-    let path = r#"assets/stages"#;
+/// The name stored inside the file, read without migrating or validating the rest;
+/// a file that can't be read at all goes by its file name.
+fn stage_name(path: &Path) -> String {
+    fs::read_to_string(path)
+        .map_err(GameError::from)
+        .and_then(|json| migration::stage_name(&json))
+        .unwrap_or_else(|| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            file_name
+                .strip_suffix(STAGE_EXTENSION)
+                .or_else(|| file_name.strip_suffix(".json"))
+                .unwrap_or(&file_name)
+                .to_string()
+        })
+}
+
 
-    Ok(Path::new(path).to_path_buf())
+fn user_stage_dir() -> GameResult<PathBuf> {
+    if let Some(dir) = stage_dir_flag(std::env::args_os()).or_else(|| std::env::var_os(STAGE_DIR_ENV)) {
+        return Ok(PathBuf::from(dir));
+    }
 
-    // let base = directories::BaseDirs::new().unwrap();
-    // let dir = base.data_local_dir();
-    // let dir = dir.join("eskate");
-    // if fs::metadata(&dir).is_err() {
-    //     fs::create_dir(&dir)?;
-    // }
-    //
-    // Ok(dir)
+    Ok(data_dir()?.join("stages"))
 }
 
 
-fn stage_path(name: &str) -> GameResult<PathBuf> {
-    let file_name = format!("{}.stage.json", name);
+/// The platform data directory for eskate, which is not created here.
+pub fn data_dir() -> GameResult<PathBuf> {
+    ProjectDirs::from("", "", "eskate")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .ok_or(GameError::MissingDataDir)
+}
+
 
-    Ok(eskate_dir_path()?.join(file_name))
+fn stage_dir_flag(args: impl IntoIterator<Item=OsString>) -> Option<OsString> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == STAGE_DIR_FLAG {
+            return args.next();
+        }
+        if let Some(dir) = arg.to_str().and_then(|arg| arg.strip_prefix(STAGE_DIR_FLAG)?.strip_prefix('=')) {
+            return Some(OsString::from(dir));
+        }
+    }
+    None
 }
 
 
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+    use std::fs;
+
+    use crate::error::GameError;
+    use crate::loader::native::{NativeStageLoader, stage_dir_flag};
+    use crate::loader::{StageLoadable, StageMetadata};
+    use crate::simulation::tests::{page, stage};
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+
+    #[test]
+    fn read_stage_dir_flag() {
+        assert_eq!(stage_dir_flag(args(&["eskate", "--stage-dir", "/tmp/stages"])), Some(OsString::from("/tmp/stages")));
+        assert_eq!(stage_dir_flag(args(&["eskate", "--stage-dir=/tmp/stages"])), Some(OsString::from("/tmp/stages")));
+        assert_eq!(stage_dir_flag(args(&["eskate", "--stage-dirs"])), None);
+        assert_eq!(stage_dir_flag(args(&["eskate"])), None);
+    }
+
+
+    #[test]
+    fn list_by_stored_name() {
+        let dir = std::env::temp_dir().join(format!("eskate-native-list-{}", std::process::id()));
+        let loader = NativeStageLoader::new(&dir);
+        let mut stage = stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]);
+        stage.name = "inside".to_string();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("outside.stage.json"), serde_json::to_string(&stage).unwrap()).unwrap();
+
+        assert_eq!(loader.list().unwrap(), vec![StageMetadata { name: "inside".to_string(), read_only: false }]);
+        loader.rename("inside", "renamed").unwrap();
+        assert_eq!(loader.load().unwrap()[0].as_ref().unwrap().name, "renamed");
+        assert!(matches!(loader.delete("inside"), Err(GameError::StageNotFound(_))));
+        loader.delete("renamed").unwrap();
+        assert!(loader.list().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+
+    #[test]
+    fn load_saved_stage() {
+        let dir = std::env::temp_dir().join(format!("eskate-native-{}", std::process::id()));
+        let loader = NativeStageLoader::new(&dir);
+        let mut stage = stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]);
+        stage.name = "mine".to_string();
+
+        loader.save(&stage).unwrap();
+        let stages = loader.load().unwrap();
+        assert_eq!(stages.len(), 1);
+        assert_eq!(*stages[0].as_ref().unwrap(), stage);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
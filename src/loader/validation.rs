@@ -0,0 +1,456 @@
+use std::collections::{BTreeMap, HashSet};
+
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::error::{GameError, GameResult};
+use crate::loader::json::{PageJson, StageJson};
+use crate::stage::playing::gimmick::tag::GimmickTag;
+
+/// Pages are drawn side by side, and no more than this fit on the screen.
+pub const MAX_PAGES: usize = 4;
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum StageIssue {
+    #[error("there is no player")]
+    MissingPlayer,
+
+    #[error("there are {0} players")]
+    MultiplePlayers(usize),
+
+    #[error("there is no goal")]
+    MissingGoal,
+
+    /// Found by the solver when saving from the editor, not by [`validate`].
+    #[error("no goal can be reached")]
+    UnreachableGoal,
+
+    #[error("there are {0} pages, but at most {MAX_PAGES} fit")]
+    TooManyPages(usize),
+
+    #[error("page {} is not closed by walls at ({col}, {row})", .page + 1)]
+    OpenBorder {
+        page: usize,
+        col: u8,
+        row: u8,
+    },
+
+    #[error("page {} has more than one cell at ({col}, {row})", .page + 1)]
+    DuplicateCell {
+        page: usize,
+        col: u8,
+        row: u8,
+    },
+
+    #[error("page {} is empty", .0 + 1)]
+    EmptyPage(usize),
+
+    #[error("the last page has a next page tile at ({col}, {row})")]
+    NextPageOnLastPage {
+        col: u8,
+        row: u8,
+    },
+
+    #[error("page {} has a {tag:?} item that can't be placed", .page + 1)]
+    UnknownItem {
+        page: usize,
+        tag: GimmickTag,
+    },
+
+    #[error("page {} has {count} teleporters on channel {channel} instead of a pair", .page + 1)]
+    UnpairedTeleporter {
+        page: usize,
+        channel: u8,
+        count: usize,
+    },
+
+    #[error("the teleporters on channel {channel} are on pages {} and {}, but a pair only works within a page", .pages.0 + 1, .pages.1 + 1)]
+    TeleportersOnDifferentPages {
+        channel: u8,
+        pages: (usize, usize),
+    },
+
+    #[error("the portal on page {} at ({col}, {row}) does not lead to a floor", .page + 1)]
+    InvalidPortalTarget {
+        page: usize,
+        col: u8,
+        row: u8,
+    },
+
+    #[error("the portal on page {} at ({col}, {row}) leads to page {}, which does not exist", .page + 1, .target_page + 1)]
+    PortalTargetOffStage {
+        page: usize,
+        col: u8,
+        row: u8,
+        target_page: usize,
+    },
+}
+
+
+/// Everything about the layout that makes a stage unplayable; an empty list means the stage is fine.
+pub fn validate(stage: &StageJson) -> Vec<StageIssue> {
+    let mut issues = Vec::new();
+
+    if MAX_PAGES < stage.pages.len() {
+        issues.push(StageIssue::TooManyPages(stage.pages.len()));
+    }
+
+    let players = count_tags(stage, GimmickTag::Player);
+    match players {
+        0 => issues.push(StageIssue::MissingPlayer),
+        1 => {}
+        _ => issues.push(StageIssue::MultiplePlayers(players)),
+    }
+
+    for (page_index, page) in stage.pages.iter().enumerate() {
+        if page.cells.is_empty() {
+            issues.push(StageIssue::EmptyPage(page_index));
+            continue;
+        }
+        issues.extend(duplicate_cells(page_index, page));
+        issues.extend(open_borders(page_index, page));
+        issues.extend(page
+            .items
+            .iter()
+            .filter(|item| !is_item(item.tag))
+            .map(|item| StageIssue::UnknownItem { page: page_index, tag: item.tag }));
+        issues.extend(invalid_portal_targets(stage, page_index, page));
+    }
+    issues.extend(unpaired_teleporters(stage));
+
+    if let Some(last) = stage.pages.last() {
+        issues.extend(last
+            .cells
+            .iter()
+            .filter(|cell| cell.tags.contains(&GimmickTag::NextPage))
+            .map(|cell| StageIssue::NextPageOnLastPage { col: cell.col, row: cell.row }));
+    }
+
+    if count_tags(stage, GimmickTag::Goal) == 0 {
+        issues.push(StageIssue::MissingGoal);
+    }
+
+    issues
+}
+
+
+/// Passes the stage through when it has no issues.
+pub fn checked(stage: StageJson) -> GameResult<StageJson> {
+    let issues = validate(&stage);
+    if issues.is_empty() {
+        Ok(stage)
+    } else {
+        Err(GameError::InvalidStage {
+            name: stage.name,
+            issues,
+        })
+    }
+}
+
+
+fn count_tags(stage: &StageJson, tag: GimmickTag) -> usize {
+    stage
+        .pages
+        .iter()
+        .flat_map(|page| page.cells.iter())
+        .map(|cell| cell.tags.iter().filter(|t| **t == tag).count())
+        .sum()
+}
+
+
+fn duplicate_cells(page_index: usize, page: &PageJson) -> Vec<StageIssue> {
+    let mut seen = HashSet::new();
+    page
+        .cells
+        .iter()
+        .filter(|cell| !seen.insert((cell.col, cell.row)))
+        .map(|cell| StageIssue::DuplicateCell { page: page_index, col: cell.col, row: cell.row })
+        .collect()
+}
+
+
+fn open_borders(page_index: usize, page: &PageJson) -> Vec<StageIssue> {
+    let walls = page
+        .cells
+        .iter()
+        .filter(|cell| cell.tags.iter().any(|tag| matches!(tag, GimmickTag::Wall | GimmickTag::WallSide)))
+        .map(|cell| (cell.col, cell.row))
+        .collect::<HashSet<(u8, u8)>>();
+
+    let w = u8::try_from(page.width() - 1).unwrap_or(u8::MAX);
+    let h = u8::try_from(page.height() - 1).unwrap_or(u8::MAX);
+    (0..=h)
+        .flat_map(|row| (0..=w).map(move |col| (col, row)))
+        .filter(|(col, row)| *col == 0 || *row == 0 || *col == w || *row == h)
+        .filter(|pos| !walls.contains(pos))
+        .map(|(col, row)| StageIssue::OpenBorder { page: page_index, col, row })
+        .collect()
+}
+
+
+/// Teleporters in the item area count too, since placing one can complete a pair.
+fn unpaired_teleporters(stage: &StageJson) -> Vec<StageIssue> {
+    // The page of every teleporter on each channel, in page order.
+    let mut channels = BTreeMap::<u8, Vec<usize>>::new();
+    for (page_index, page) in stage.pages.iter().enumerate() {
+        let cells = page
+            .cells
+            .iter()
+            .filter(|cell| cell.tags.contains(&GimmickTag::Teleporter))
+            .map(|cell| cell.properties(GimmickTag::Teleporter));
+        let items = page
+            .items
+            .iter()
+            .filter(|item| item.tag == GimmickTag::Teleporter)
+            .map(|item| item.properties);
+        for properties in cells.chain(items) {
+            channels.entry(properties.channel.unwrap_or_default()).or_default().push(page_index);
+        }
+    }
+
+    channels
+        .into_iter()
+        .flat_map(|(channel, pages)| match pages[..] {
+            [first, second] if first != second => vec![StageIssue::TeleportersOnDifferentPages { channel, pages: (first, second) }],
+            _ => pages
+                .into_iter()
+                .dedup_with_count()
+                .filter(|(count, _)| *count != 2)
+                .map(|(count, page)| StageIssue::UnpairedTeleporter { page, channel, count })
+                .collect()
+        })
+        .collect()
+}
+
+
+/// A portal needs a target, on a page of the stage, at a cell with a floor to land on.
+fn invalid_portal_targets(stage: &StageJson, page_index: usize, page: &PageJson) -> Vec<StageIssue> {
+    page
+        .cells
+        .iter()
+        .filter(|cell| cell.tags.contains(&GimmickTag::Portal))
+        .filter_map(|cell| {
+            let (col, row) = (cell.col, cell.row);
+            let Some(target) = cell.properties(GimmickTag::Portal).target else {
+                return Some(StageIssue::InvalidPortalTarget { page: page_index, col, row });
+            };
+            let Some(target_page) = stage.pages.get(target.page) else {
+                return Some(StageIssue::PortalTargetOffStage { page: page_index, col, row, target_page: target.page });
+            };
+            let lands_on_floor = target_page
+                .cells
+                .iter()
+                .any(|cell| (cell.col, cell.row) == (target.col, target.row) && cell.tags.contains(&GimmickTag::Floor));
+            (!lands_on_floor).then_some(StageIssue::InvalidPortalTarget { page: page_index, col, row })
+        })
+        .collect()
+}
+
+
+#[inline]
+fn is_item(tag: GimmickTag) -> bool {
+    !matches!(tag, GimmickTag::Floor | GimmickTag::Wall | GimmickTag::WallSide | GimmickTag::Player)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::{StageLoadable, StageLoader};
+    use crate::loader::json::{GimmickProperties, ItemCell, PortalTarget, StageCell, StageJson};
+    use crate::loader::validation::{MAX_PAGES, StageIssue, validate};
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+
+    #[test]
+    fn valid_stage() {
+        let stage = stage(vec![page(&[
+            "#####",
+            "#P..#",
+            "#..G#",
+            "#####",
+        ], &[GimmickTag::Rock])]);
+
+        assert_eq!(validate(&stage), vec![]);
+    }
+
+
+    #[test]
+    fn missing_player_and_goal() {
+        let stage = stage(vec![page(&[
+            "###",
+            "#.#",
+            "###",
+        ], &[])]);
+
+        assert_eq!(validate(&stage), vec![StageIssue::MissingPlayer, StageIssue::MissingGoal]);
+    }
+
+
+    #[test]
+    fn too_many_pages() {
+        let mut pages = vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])];
+        pages.extend((0..MAX_PAGES).map(|_| page(&[
+            "###",
+            "#.#",
+            "###",
+        ], &[])));
+
+        assert_eq!(validate(&stage(pages)), vec![StageIssue::TooManyPages(MAX_PAGES + 1)]);
+    }
+
+
+    #[test]
+    fn widest_page() {
+        let mut page = page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[]);
+        page.cells.push(StageCell::new(u8::MAX, 0, vec![GimmickTag::Wall]));
+
+        assert_eq!(page.width(), 256);
+        assert!(validate(&stage(vec![page])).contains(&StageIssue::OpenBorder { page: 0, col: u8::MAX, row: 1 }));
+    }
+
+
+    #[test]
+    fn open_border() {
+        let stage = stage(vec![page(&[
+            "#####",
+            "#P.G.",
+            "#####",
+        ], &[])]);
+
+        assert_eq!(validate(&stage), vec![StageIssue::OpenBorder { page: 0, col: 4, row: 1 }]);
+    }
+
+
+    #[test]
+    fn duplicate_cell_and_unknown_item() {
+        let mut page = page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[]);
+        page.cells.push(page.cells[0].clone());
+        page.items.push(ItemCell { row: 0, tag: GimmickTag::Player, properties: Default::default() });
+
+        assert_eq!(validate(&stage(vec![page])), vec![
+            StageIssue::DuplicateCell { page: 0, col: 0, row: 0 },
+            StageIssue::UnknownItem { page: 0, tag: GimmickTag::Player },
+        ]);
+    }
+
+
+    #[test]
+    fn next_page_on_last_page_and_empty_page() {
+        let stage = stage(vec![
+            page(&[], &[]),
+            page(&[
+                "#####",
+                "#P.G#",
+                "#.N.#",
+                "#####",
+            ], &[]),
+        ]);
+
+        assert_eq!(validate(&stage), vec![
+            StageIssue::EmptyPage(0),
+            StageIssue::NextPageOnLastPage { col: 2, row: 2 },
+        ]);
+    }
+
+
+    #[test]
+    fn unpaired_teleporter() {
+        let stage = stage(vec![page(&[
+            "######",
+            "#PX.G#",
+            "######",
+        ], &[])]);
+
+        assert_eq!(validate(&stage), vec![StageIssue::UnpairedTeleporter { page: 0, channel: 0, count: 1 }]);
+    }
+
+
+    #[test]
+    fn teleporters_on_different_pages() {
+        let stage = stage(vec![
+            page(&[
+                "######",
+                "#PXN.#",
+                "######",
+            ], &[]),
+            page(&[
+                "######",
+                "#.X.G#",
+                "######",
+            ], &[GimmickTag::Teleporter]),
+        ]);
+
+        // The item pairs up with the teleporter on its own page.
+        assert_eq!(validate(&stage), vec![StageIssue::UnpairedTeleporter { page: 0, channel: 0, count: 1 }]);
+
+        let mut stage = stage;
+        stage.pages[1].items.clear();
+        assert_eq!(validate(&stage), vec![StageIssue::TeleportersOnDifferentPages { channel: 0, pages: (0, 1) }]);
+    }
+
+
+    #[test]
+    fn portal_without_floor_target() {
+        let mut page = page(&[
+            "#####",
+            "#P.G#",
+            "#...#",
+            "#####",
+        ], &[]);
+        let portal = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 2)).unwrap();
+        portal.tags.push(GimmickTag::Portal);
+        portal.properties.insert(GimmickTag::Portal, GimmickProperties { target: Some(PortalTarget { page: 0, col: 0, row: 0 }), ..Default::default() });
+
+        assert_eq!(validate(&stage(vec![page])), vec![StageIssue::InvalidPortalTarget { page: 0, col: 2, row: 2 }]);
+    }
+
+
+    #[test]
+    fn portal_target_checked() {
+        let mut page = page(&[
+            "#####",
+            "#P.G#",
+            "#...#",
+            "#####",
+        ], &[]);
+        let portal = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 2)).unwrap();
+        portal.tags.push(GimmickTag::Portal);
+        let mut stage = stage(vec![page]);
+        let target = |stage: &mut StageJson, target: Option<PortalTarget>| {
+            let portal = stage.pages[0].cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 2)).unwrap();
+            portal.properties.insert(GimmickTag::Portal, GimmickProperties { target, ..Default::default() });
+        };
+
+        assert_eq!(validate(&stage), vec![StageIssue::InvalidPortalTarget { page: 0, col: 2, row: 2 }]);
+
+        target(&mut stage, Some(PortalTarget { page: 1, col: 1, row: 2 }));
+        assert_eq!(validate(&stage), vec![StageIssue::PortalTargetOffStage { page: 0, col: 2, row: 2, target_page: 1 }]);
+
+        target(&mut stage, Some(PortalTarget { page: 0, col: 9, row: 9 }));
+        assert_eq!(validate(&stage), vec![StageIssue::InvalidPortalTarget { page: 0, col: 2, row: 2 }]);
+
+        target(&mut stage, Some(PortalTarget { page: 0, col: 1, row: 2 }));
+        assert_eq!(validate(&stage), vec![]);
+    }
+
+
+    #[test]
+    fn built_in_stages_are_valid() {
+        for stage in StageLoader::built_in().load().unwrap().into_iter().map(Result::unwrap) {
+            assert_eq!(validate(&stage), vec![], "{}", stage.name);
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use wasm_bindgen::JsValue;
+use web_sys::Storage;
+
+use crate::error::{GameError, GameResult};
+use crate::loader::json::StageJson;
+use crate::loader::{migration, StageLoadable, StageLoadError, StageLoadResult, StageMetadata, validation};
+
+const KEY_PREFIX: &str = "eskate.stage.";
+
+
+/// Keeps user stages in the browser's local storage, one key per stage.
+///
+/// The storage is looked up on every call rather than held, so the loader can live in a resource.
+#[derive(Debug, Copy, Clone, Default, Hash, Eq, PartialEq)]
+pub struct WebStageLoader;
+
+
+impl WebStageLoader {
+    pub fn user() -> GameResult<Self> {
+        local_storage()?;
+        Ok(Self)
+    }
+
+
+    fn names(&self) -> GameResult<Vec<String>> {
+        let storage = local_storage()?;
+        let mut names = Vec::new();
+        for i in 0..storage.length().map_err(js_error)? {
+            if let Some(name) = storage.key(i).map_err(js_error)?.and_then(|key| key.strip_prefix(KEY_PREFIX).map(str::to_string)) {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+
+    fn read(&self, name: &str) -> GameResult<String> {
+        local_storage()?
+            .get_item(&key(name))
+            .map_err(js_error)?
+            .ok_or_else(|| GameError::StageNotFound(name.to_string()))
+    }
+}
+
+
+impl StageLoadable for WebStageLoader {
+    fn list(&self) -> GameResult<Vec<StageMetadata>> {
+        Ok(self
+            .names()?
+            .into_iter()
+            .map(|name| StageMetadata {
+                name,
+                read_only: false,
+            })
+            .collect())
+    }
+
+
+    fn load(&self) -> GameResult<Vec<StageLoadResult>> {
+        Ok(self
+            .names()?
+            .into_iter()
+            .map(|name| self
+                .read(&name)
+                .and_then(|json| migration::from_json(&json))
+                .and_then(validation::checked)
+                .map_err(|e| StageLoadError::new(key(&name), e)))
+            .collect())
+    }
+
+
+    fn save(&self, json: &StageJson) -> GameResult {
+        local_storage()?
+            .set_item(&key(&json.name), &serde_json::to_string(json)?)
+            .map_err(js_error)
+    }
+
+
+    fn delete(&self, name: &str) -> GameResult {
+        self.read(name)?;
+        local_storage()?.remove_item(&key(name)).map_err(js_error)
+    }
+
+
+    fn rename(&self, from: &str, to: &str) -> GameResult {
+        if local_storage()?.get_item(&key(to)).map_err(js_error)?.is_some() {
+            return Err(GameError::StageExists(to.to_string()));
+        }
+
+        let mut stage = migration::from_json(&self.read(from)?)?;
+        stage.name = to.to_string();
+        self.save(&stage)?;
+        self.delete(from)
+    }
+}
+
+
+pub fn local_storage() -> GameResult<Storage> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or(GameError::MissingWebStorage)
+}
+
+
+#[inline]
+fn key(name: &str) -> String {
+    format!("{KEY_PREFIX}{name}")
+}
+
+
+pub fn js_error(error: JsValue) -> GameError {
+    GameError::WebStorage(error.as_string().unwrap_or_else(|| format!("{error:?}")))
+}
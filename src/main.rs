@@ -7,7 +7,7 @@ use bevy::asset::Handle;
 use bevy::DefaultPlugins;
 use bevy::ecs::system::SystemParam;
 use bevy::input::Input;
-use bevy::prelude::{Assets, AssetServer, Camera, Camera2dBundle, Commands, Component, Entity, Image, in_state, IntoSystemConfigs, MouseButton, not, OnExit, Query, Res, ResMut, UiImage, With, Without};
+use bevy::prelude::{Assets, AssetServer, Camera, Camera2dBundle, Commands, Component, Entity, Image, in_state, IntoSystemConfigs, MouseButton, not, OnExit, Query, Res, UiImage, With, Without};
 use bevy::ui::{Style, Val};
 use bevy::utils::default;
 use bevy::window::{Cursor, Window, WindowPlugin, WindowResolution};
@@ -19,17 +19,20 @@ use bevy_undo2::UndoPlugin;
 use crate::assets::cursor::CursorAssets;
 use crate::assets::font::FontAssets;
 use crate::assets::gimmick::GimmickAssets;
-use crate::assets::stage::{BuiltInStages, StageAssets};
+use crate::assets::stage::{CATALOGUE_PATH, StageAssets, StageCatalogue};
 use crate::assets::stage_edit_assets::StageEditAssets;
 use crate::before_stage_edit::BeforeStageEditPlugin;
 use crate::button::SpriteButtonPlugin;
 use crate::cursor::{GameCursor, GameCursorBundle};
 use crate::gama_state::GameState;
+use crate::progress::ProgressPlugin;
 use crate::loader::json::StageJson;
+use crate::loader::StageLoader;
 use crate::stage::StagePlugin;
 use crate::stage_edit::StageEditPlugin;
 use crate::stage_select::StageSelectPlugin;
 use crate::title::TitlePlugin;
+use crate::window::{WINDOW_HEIGHT, WINDOW_WIDTH};
 
 mod gama_state;
 mod title;
@@ -46,13 +49,15 @@ mod before_stage_edit;
 mod cursor;
 mod undo;
 mod window;
+mod simulation;
+mod progress;
 
 
 fn main() {
     let default_plugins = DefaultPlugins.set(WindowPlugin {
         primary_window: Some(Window {
             resizable: false,
-            resolution: WindowResolution::new(1200., 800.),
+            resolution: WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT),
             title: "eskate".to_string(),
             ..default()
         }),
@@ -72,11 +77,13 @@ fn main() {
         )
         .add_collection_to_loading_state::<_, GimmickAssets>(GameState::AssetLoading)
         .add_collection_to_loading_state::<_, FontAssets>(GameState::AssetLoading)
+        .add_dynamic_collection_to_loading_state::<_, StageCatalogue>(GameState::AssetLoading, CATALOGUE_PATH)
         .add_collection_to_loading_state::<_, StageAssets>(GameState::AssetLoading)
         .add_collection_to_loading_state::<_, StageEditAssets>(GameState::AssetLoading)
         .add_collection_to_loading_state::<_, CursorAssets>(GameState::AssetLoading)
         .add_plugins((
             JsonAssetPlugin::<StageJson>::new(&["stage.json"]),
+            JsonAssetPlugin::<StageCatalogue>::new(&["catalogue.json"]),
             // bevy_inspector_egui::quick::WorldInspectorPlugin::new(),
             TweeningPlugin,
             UndoPlugin,
@@ -87,8 +94,10 @@ fn main() {
             BeforeStageEditPlugin,
             StageEditPlugin,
             StageSelectPlugin,
-            StagePlugin
+            StagePlugin,
+            ProgressPlugin
         ))
+        .insert_resource(StageLoader::new())
         .add_systems(OnExit(GameState::AssetLoading), setup)
         .add_systems(Update, move_cursor.run_if(not(in_state(GameState::AssetLoading))))
         .add_state::<GameState>()
@@ -102,7 +111,8 @@ pub struct MainCamera;
 
 fn setup(
     mut commands: Commands,
-    mut stage: ResMut<Assets<StageJson>>,
+    catalogues: Res<Assets<StageCatalogue>>,
+    stage_jsons: Res<Assets<StageJson>>,
     stages: Res<StageAssets>,
     asset_server: Res<AssetServer>,
 ) {
@@ -111,7 +121,7 @@ fn setup(
         .insert(MainCamera);
 
     commands.spawn(GameCursorBundle::new(&asset_server));
-    commands.insert_resource(BuiltInStages(stages.stages(&mut stage)));
+    commands.insert_resource(stages.built_in_stages(&catalogues, &stage_jsons));
 }
 
 
@@ -44,6 +44,14 @@ impl<'w> PageParams<'w> {
     }
 
 
+    /// Jumps to any page; indices past the last page are clamped to it.
+    #[inline]
+    pub fn move_to(&mut self, page_index: PageIndex) -> PageIndex {
+        *self.page_index = PageIndex::new((*page_index).min((**self.page_count).saturating_sub(1)));
+        *self.page_index
+    }
+
+
     #[inline]
     pub fn page_count(&self) -> usize {
         **self.page_count
@@ -51,7 +59,6 @@ impl<'w> PageParams<'w> {
 
 
     #[inline]
-    #[allow(unused)]
     pub fn page_index(&self) -> usize {
         **self.page_index
     }
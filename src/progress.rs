@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::{Component, IntoSystemConfigs, not, Res, Resource, resource_added, resource_changed};
+use serde::{Deserialize, Serialize};
+
+use crate::error::GameResult;
+
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct ProgressPlugin;
+
+
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Progress::load().unwrap_or_else(|e| {
+                bevy::log::warn!("progress could not be loaded: {e}");
+                Progress::default()
+            }))
+            .add_systems(Update, save_progress_system
+                .run_if(resource_changed::<Progress>().and_then(not(resource_added::<Progress>()))));
+    }
+}
+
+
+/// How a single clear went.
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StageResult {
+    pub moves: usize,
+    pub undos: usize,
+    pub hints: usize,
+    pub time: Duration,
+}
+
+
+/// The best of every clear of a stage; each field may come from a different clear.
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StageRecord {
+    pub clears: u32,
+    pub best_moves: usize,
+    pub fewest_undos: usize,
+    pub best_time: Duration,
+}
+
+
+/// Which stage is being played, kept across edits of its file.
+///
+/// Built-in stages go by their catalogue id; user stages by their name prefixed with the source,
+/// since a user stage may share its name with a built-in one.
+#[derive(Resource, Component, Serialize, Deserialize, Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(transparent)]
+pub struct StageId(String);
+
+
+impl StageId {
+    #[inline]
+    pub fn built_in(id: &str) -> Self {
+        Self(id.to_string())
+    }
+
+
+    #[inline]
+    pub fn user(name: &str) -> Self {
+        Self(format!("user/{name}"))
+    }
+}
+
+
+/// Cleared stages keyed by [`StageId`].
+#[derive(Resource, Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Progress {
+    #[serde(default)]
+    stages: BTreeMap<StageId, StageRecord>,
+}
+
+
+impl Progress {
+    #[inline]
+    pub fn is_cleared(&self, id: &StageId) -> bool {
+        self.stages.contains_key(id)
+    }
+
+
+    #[inline]
+    pub fn record(&self, id: &StageId) -> Option<&StageRecord> {
+        self.stages.get(id)
+    }
+
+
+    pub fn add_clear(&mut self, id: &StageId, result: StageResult) {
+        match self.stages.get_mut(id) {
+            Some(record) => {
+                record.clears += 1;
+                record.best_moves = record.best_moves.min(result.moves);
+                record.fewest_undos = record.fewest_undos.min(result.undos);
+                record.best_time = record.best_time.min(result.time);
+            }
+            None => {
+                self.stages.insert(id.clone(), StageRecord {
+                    clears: 1,
+                    best_moves: result.moves,
+                    fewest_undos: result.undos,
+                    best_time: result.time,
+                });
+            }
+        }
+    }
+
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load() -> GameResult<Self> {
+        let path = progress_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self) -> GameResult {
+        let path = progress_path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+
+    #[cfg(target_arch = "wasm32")]
+    fn load() -> GameResult<Self> {
+        use crate::loader::web::{js_error, local_storage};
+
+        match local_storage()?.get_item(PROGRESS_KEY).map_err(js_error)? {
+            Some(json) => Ok(serde_json::from_str(&json)?),
+            None => Ok(Self::default())
+        }
+    }
+
+
+    #[cfg(target_arch = "wasm32")]
+    fn save(&self) -> GameResult {
+        use crate::loader::web::{js_error, local_storage};
+
+        local_storage()?
+            .set_item(PROGRESS_KEY, &serde_json::to_string(self)?)
+            .map_err(js_error)
+    }
+}
+
+
+#[cfg(target_arch = "wasm32")]
+const PROGRESS_KEY: &str = "eskate.progress";
+
+
+#[cfg(not(target_arch = "wasm32"))]
+fn progress_path() -> GameResult<std::path::PathBuf> {
+    Ok(crate::loader::native::data_dir()?.join("progress.json"))
+}
+
+
+fn save_progress_system(progress: Res<Progress>) {
+    if let Err(e) = progress.save() {
+        bevy::log::warn!("progress could not be saved: {e}");
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::progress::{Progress, StageId, StageRecord, StageResult};
+
+    #[test]
+    fn keep_best_of_each_clear() {
+        let stage1 = StageId::built_in("stage1");
+        let stage2 = StageId::built_in("stage2");
+        let mut progress = Progress::default();
+        progress.add_clear(&stage1, StageResult { moves: 10, undos: 0, hints: 0, time: Duration::from_secs(30) });
+        progress.add_clear(&stage1, StageResult { moves: 7, undos: 2, hints: 1, time: Duration::from_secs(40) });
+
+        assert!(progress.is_cleared(&stage1));
+        assert!(!progress.is_cleared(&stage2));
+        assert_eq!(progress.record(&stage1), Some(&StageRecord {
+            clears: 2,
+            best_moves: 7,
+            fewest_undos: 0,
+            best_time: Duration::from_secs(30),
+        }));
+    }
+
+
+    #[test]
+    fn same_name_is_another_stage() {
+        let mut progress = Progress::default();
+        progress.add_clear(&StageId::built_in("stage1"), StageResult::default());
+
+        assert!(progress.is_cleared(&StageId::built_in("stage1")));
+        assert!(!progress.is_cleared(&StageId::user("stage1")));
+    }
+
+
+    #[test]
+    fn keyed_by_id_in_json() {
+        let mut progress = Progress::default();
+        progress.add_clear(&StageId::user("mine"), StageResult::default());
+
+        let json = serde_json::to_value(&progress).unwrap();
+        assert!(json["stages"].get("user/mine").is_some());
+        assert_eq!(serde_json::from_value::<Progress>(json).unwrap(), progress);
+    }
+}
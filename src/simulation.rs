@@ -0,0 +1,141 @@
+use crate::error::GameResult;
+use crate::loader::json::StageJson;
+use crate::simulation::board::{Board, BoardState};
+use crate::simulation::grid::GridPos;
+use crate::simulation::outcome::Outcome;
+use crate::stage::playing::move_direction::MoveDirection;
+
+pub mod action;
+pub mod board;
+pub mod grid;
+pub mod motion;
+pub mod outcome;
+pub mod solver;
+
+
+/// Plays a stage without a Bevy `App`.
+///
+/// `Board` is the only place the rules live; `stage::playing` asks it for the
+/// [`Motion`](motion::Motion)s of each move and only animates them.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    board: Board,
+    state: BoardState,
+}
+
+
+impl Simulation {
+    pub fn new(stage: &StageJson) -> GameResult<Self> {
+        let board = Board::new(stage)?;
+        let state = board.initial_state();
+        Ok(Self {
+            board,
+            state,
+        })
+    }
+
+
+    #[inline]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+
+    #[inline]
+    pub fn state(&self) -> &BoardState {
+        &self.state
+    }
+
+
+    #[inline]
+    pub fn step(&mut self, direction: MoveDirection) -> Outcome {
+        self.board.step(&mut self.state, direction)
+    }
+
+
+    #[inline]
+    pub fn place(&mut self, item_index: usize, pos: GridPos) -> Outcome {
+        self.board.place(&mut self.state, item_index, pos)
+    }
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::loader::json::{GimmickProperties, ItemCell, PageJson, STAGE_VERSION, StageCell, StageJson};
+    use crate::simulation::grid::GridPos;
+    use crate::simulation::outcome::Outcome;
+    use crate::simulation::Simulation;
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+
+    /// Builds a page from ascii rows. `L` is a lock covering a goal, as in the built-in stages.
+    pub(crate) fn page(rows: &[&str], items: &[GimmickTag]) -> PageJson {
+        let mut cells = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let tags = match c {
+                    '#' => vec![GimmickTag::Wall],
+                    '.' => vec![GimmickTag::Floor],
+                    'P' => vec![GimmickTag::Floor, GimmickTag::Player],
+                    'G' => vec![GimmickTag::Floor, GimmickTag::Goal],
+                    'R' => vec![GimmickTag::Floor, GimmickTag::Rock],
+                    'S' => vec![GimmickTag::Floor, GimmickTag::Stop],
+                    'I' => vec![GimmickTag::Floor, GimmickTag::IceBox],
+                    'T' => vec![GimmickTag::Floor, GimmickTag::Turn],
+                    'K' => vec![GimmickTag::Floor, GimmickTag::Key],
+                    'L' => vec![GimmickTag::Floor, GimmickTag::Goal, GimmickTag::Lock],
+                    'N' => vec![GimmickTag::Floor, GimmickTag::NextPage],
+                    'O' => vec![GimmickTag::Floor, GimmickTag::Hole],
+                    'X' => vec![GimmickTag::Floor, GimmickTag::Teleporter],
+                    'A' => vec![GimmickTag::Floor, GimmickTag::Arrow],
+                    '=' => vec![GimmickTag::Floor, GimmickTag::Gate],
+                    _ => continue,
+                };
+                cells.push(StageCell::new(x as u8, y as u8, tags));
+            }
+        }
+
+        PageJson {
+            cells,
+            items: items
+                .iter()
+                .enumerate()
+                .map(|(row, tag)| ItemCell { row: row as u8, tag: *tag, properties: Default::default() })
+                .collect(),
+        }
+    }
+
+
+    /// Turns the topmost gimmick of the cell at `(col, row)` to face `direction`.
+    pub(crate) fn rotate(page: &mut PageJson, (col, row): (u8, u8), direction: MoveDirection) {
+        let cell = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (col, row)).unwrap();
+        let tag = *cell.tags.last().unwrap();
+        cell.properties.insert(tag, GimmickProperties { rotation: Some(direction), ..Default::default() });
+    }
+
+
+    pub(crate) fn stage(pages: Vec<PageJson>) -> StageJson {
+        StageJson {
+            version: STAGE_VERSION,
+            name: "test".to_string(),
+            pages,
+            hints: None,
+        }
+    }
+
+
+    #[test]
+    fn play_without_app() {
+        let mut simulation = Simulation::new(&stage(vec![page(&[
+            "#####",
+            "#P..#",
+            "#..G#",
+            "#####",
+        ], &[])])).unwrap();
+
+        assert_eq!(simulation.step(MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(simulation.step(MoveDirection::Down), Outcome::Goaled);
+        assert!(simulation.state().goaled);
+    }
+}
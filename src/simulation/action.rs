@@ -0,0 +1,13 @@
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::move_direction::MoveDirection;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Action {
+    Move(MoveDirection),
+
+    /// Places the `item_index`-th item of the current page's item area on `pos`.
+    Place {
+        item_index: usize,
+        pos: GridPos,
+    },
+}
@@ -0,0 +1,970 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+
+use crate::error::{GameError, GameResult};
+use crate::loader::json::{GimmickProperties, PageJson, StageJson};
+use crate::simulation::action::Action;
+use crate::simulation::grid::GridPos;
+use crate::simulation::motion::Motion;
+use crate::simulation::outcome::Outcome;
+use crate::stage::playing::collide::GimmickCollide;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::move_direction::MoveDirection;
+
+/// Where the player ends up after hitting a collider.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Landing {
+    InFront,
+    OnTop,
+    /// On the collider's cell like `OnTop`, but drawn on its layer so the player sinks into it.
+    Into,
+}
+
+
+#[inline]
+pub fn collide(tag: GimmickTag) -> Option<(GimmickCollide, Landing)> {
+    match tag {
+        GimmickTag::Floor | GimmickTag::Player => None,
+        GimmickTag::Wall | GimmickTag::WallSide | GimmickTag::Rock => Some((GimmickCollide::StopMove, Landing::InFront)),
+        GimmickTag::IceBox => Some((GimmickCollide::IceBox, Landing::InFront)),
+        GimmickTag::Stop => Some((GimmickCollide::StopMove, Landing::OnTop)),
+        GimmickTag::NextPage => Some((GimmickCollide::NextPage, Landing::OnTop)),
+        GimmickTag::Goal => Some((GimmickCollide::Goal, Landing::OnTop)),
+        GimmickTag::Turn => Some((GimmickCollide::Turn, Landing::OnTop)),
+        GimmickTag::Key => Some((GimmickCollide::Key, Landing::OnTop)),
+        GimmickTag::Lock => Some((GimmickCollide::Lock, Landing::OnTop)),
+        GimmickTag::Hole => Some((GimmickCollide::Hole, Landing::Into)),
+        GimmickTag::Teleporter => Some((GimmickCollide::Teleport, Landing::OnTop)),
+        GimmickTag::Portal => Some((GimmickCollide::Portal, Landing::OnTop)),
+        GimmickTag::Arrow => Some((GimmickCollide::Arrow, Landing::OnTop)),
+        GimmickTag::Gate => Some((GimmickCollide::StopMove, Landing::InFront)),
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BoardPage {
+    cells: BTreeMap<GridPos, Vec<GimmickTag>>,
+    properties: BTreeMap<(GridPos, GimmickTag), GimmickProperties>,
+    items: Vec<(GimmickTag, GimmickProperties)>,
+    min: GridPos,
+    max: GridPos,
+}
+
+
+impl BoardPage {
+    pub fn new(page: &PageJson) -> Self {
+        let cells = page
+            .cells
+            .iter()
+            .map(|cell| (GridPos::from_cell(cell), cell.tags.iter().copied().sorted().collect::<Vec<GimmickTag>>()))
+            .collect::<BTreeMap<GridPos, Vec<GimmickTag>>>();
+
+        let properties = page
+            .cells
+            .iter()
+            .flat_map(|cell| cell
+                .properties
+                .iter()
+                .map(|(tag, properties)| ((GridPos::from_cell(cell), *tag), *properties)))
+            .collect::<BTreeMap<(GridPos, GimmickTag), GimmickProperties>>();
+
+        let min = GridPos::new(
+            cells.keys().map(|pos| pos.x).min().unwrap_or_default(),
+            cells.keys().map(|pos| pos.y).min().unwrap_or_default(),
+        );
+        let max = GridPos::new(
+            cells.keys().map(|pos| pos.x).max().unwrap_or_default(),
+            cells.keys().map(|pos| pos.y).max().unwrap_or_default(),
+        );
+
+        Self {
+            cells,
+            properties,
+            items: page.items.iter().map(|item| (item.tag, item.properties)).collect(),
+            min,
+            max,
+        }
+    }
+
+
+    #[inline]
+    pub fn tags(&self, pos: GridPos) -> &[GimmickTag] {
+        self.cells.get(&pos).map(|tags| tags.as_slice()).unwrap_or_default()
+    }
+
+
+    #[inline]
+    pub fn properties(&self, pos: GridPos, tag: GimmickTag) -> GimmickProperties {
+        self.properties.get(&(pos, tag)).copied().unwrap_or_default()
+    }
+
+
+    #[inline]
+    pub fn items(&self) -> &[(GimmickTag, GimmickProperties)] {
+        &self.items
+    }
+
+
+    #[inline]
+    pub fn cells(&self) -> impl Iterator<Item=(&GridPos, &Vec<GimmickTag>)> {
+        self.cells.iter()
+    }
+
+
+    #[inline]
+    pub fn is_floor(&self, pos: GridPos) -> bool {
+        self.tags(pos).contains(&GimmickTag::Floor)
+    }
+
+
+    #[inline]
+    pub fn contains(&self, pos: GridPos) -> bool {
+        (self.min.x..=self.max.x).contains(&pos.x) && (self.min.y..=self.max.y).contains(&pos.y)
+    }
+
+
+    #[inline]
+    fn find(&self, tag: GimmickTag) -> Option<GridPos> {
+        self
+            .cells
+            .iter()
+            .find(|(_, tags)| tags.contains(&tag))
+            .map(|(pos, _)| *pos)
+    }
+}
+
+
+/// Everything about a stage that changes while playing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BoardState {
+    pub page_index: usize,
+    pub player: GridPos,
+    pub goaled: bool,
+    keys: BTreeMap<u8, usize>,
+    removed: BTreeSet<(usize, GridPos, GimmickTag)>,
+    used_items: BTreeSet<(usize, usize)>,
+    placed: Vec<(GridPos, GimmickTag, GimmickProperties)>,
+    ice_boxes: BTreeSet<(usize, GridPos)>,
+}
+
+
+impl BoardState {
+    /// Keys held of the given colour, see `KeyColor`.
+    #[inline]
+    pub fn keys(&self, channel: u8) -> usize {
+        self.keys.get(&channel).copied().unwrap_or_default()
+    }
+
+
+    #[inline]
+    pub fn is_item_used(&self, page_index: usize, item_index: usize) -> bool {
+        self.used_items.contains(&(page_index, item_index))
+    }
+
+
+    #[inline]
+    pub fn is_removed(&self, page_index: usize, pos: GridPos, tag: GimmickTag) -> bool {
+        self.removed.contains(&(page_index, pos, tag))
+    }
+
+
+    /// Items placed on the board; like `GimmickItemSpawned`, they always follow the player's page.
+    #[inline]
+    pub fn placed(&self) -> &[(GridPos, GimmickTag, GimmickProperties)] {
+        &self.placed
+    }
+
+
+    /// Hands over what a running stage holds besides its layout, for states read back from the ECS.
+    pub fn carry(
+        &mut self,
+        keys: impl IntoIterator<Item=(u8, usize)>,
+        placed: impl IntoIterator<Item=(GridPos, GimmickTag, GimmickProperties)>,
+    ) {
+        self.keys.extend(keys.into_iter().filter(|(_, count)| 0 < *count));
+        self.placed.extend(placed);
+    }
+
+
+    /// Current cells of the ice boxes from the stage itself; placed ice boxes stay in `placed`.
+    #[inline]
+    pub fn ice_boxes(&self) -> impl Iterator<Item=&(usize, GridPos)> {
+        self.ice_boxes.iter()
+    }
+}
+
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Source {
+    Cell,
+    Placed(usize),
+    IceBox,
+}
+
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Collider {
+    tag: GimmickTag,
+    properties: GimmickProperties,
+    source: Source,
+}
+
+
+impl Collider {
+    /// Arrows and gates let the player through when moving the way they face.
+    #[inline]
+    fn passes(&self, direction: MoveDirection) -> bool {
+        matches!(self.tag, GimmickTag::Arrow | GimmickTag::Gate) && self.properties.rotation.unwrap_or(MoveDirection::Up) == direction
+    }
+}
+
+
+enum Hit {
+    Continue(MoveDirection),
+    Done(Outcome),
+}
+
+
+/// The static layout of a stage, built from its `StageJson`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    pages: Vec<BoardPage>,
+    start: (usize, GridPos),
+}
+
+
+impl Board {
+    pub fn new(stage: &StageJson) -> GameResult<Self> {
+        let pages = stage
+            .pages
+            .iter()
+            .map(BoardPage::new)
+            .collect::<Vec<BoardPage>>();
+
+        let start = pages
+            .iter()
+            .enumerate()
+            .find_map(|(page_index, page)| page.find(GimmickTag::Player).map(|pos| (page_index, pos)))
+            .ok_or_else(|| GameError::MissingPlayer(stage.name.clone()))?;
+
+        Ok(Self {
+            pages,
+            start,
+        })
+    }
+
+
+    #[inline]
+    pub fn pages(&self) -> &[BoardPage] {
+        &self.pages
+    }
+
+
+    pub fn initial_state(&self) -> BoardState {
+        let (page_index, player) = self.start;
+        BoardState {
+            page_index,
+            player,
+            goaled: false,
+            keys: BTreeMap::new(),
+            removed: BTreeSet::new(),
+            used_items: BTreeSet::new(),
+            placed: Vec::new(),
+            ice_boxes: self
+                .pages
+                .iter()
+                .enumerate()
+                .flat_map(|(page_index, page)| page
+                    .cells
+                    .iter()
+                    .filter(|(_, tags)| tags.contains(&GimmickTag::IceBox))
+                    .map(move |(pos, _)| (page_index, *pos)))
+                .collect(),
+        }
+    }
+
+
+    #[inline]
+    pub fn apply(&self, state: &mut BoardState, action: Action) -> Outcome {
+        match action {
+            Action::Move(direction) => self.step(state, direction),
+            Action::Place { item_index, pos } => self.place(state, item_index, pos),
+        }
+    }
+
+
+    /// Every action that is worth trying from `state`: the four moves followed by each unused item on each floor.
+    pub fn actions(&self, state: &BoardState) -> Vec<Action> {
+        let page = &self.pages[state.page_index];
+        let mut actions = vec![
+            Action::Move(MoveDirection::Left),
+            Action::Move(MoveDirection::Up),
+            Action::Move(MoveDirection::Right),
+            Action::Move(MoveDirection::Down),
+        ];
+
+        for item_index in (0..page.items.len()).filter(|item_index| !state.is_item_used(state.page_index, *item_index)) {
+            actions.extend(page
+                .cells
+                .iter()
+                .filter(|(_, tags)| tags.contains(&GimmickTag::Floor))
+                .map(|(pos, _)| Action::Place { item_index, pos: *pos }));
+        }
+
+        actions
+    }
+
+
+    #[inline]
+    pub fn step(&self, state: &mut BoardState, direction: MoveDirection) -> Outcome {
+        self.trace(state, direction, &mut |_| {})
+    }
+
+
+    /// Like [`Board::step`], handing every [`Motion`] of the move to `record` as it happens.
+    ///
+    /// A move that ends in a hole is still recorded up to the hole, even though `state` is put back.
+    pub fn trace(&self, state: &mut BoardState, direction: MoveDirection, record: &mut impl FnMut(Motion)) -> Outcome {
+        if state.goaled {
+            return Outcome::Goaled;
+        }
+
+        let before = state.clone();
+        let mut direction = direction;
+        let mut moved = false;
+        let mut visited = BTreeSet::new();
+
+        loop {
+            if !visited.insert((state.player, direction, state.keys.values().sum::<usize>(), state.removed.len(), state.placed.len())) {
+                return Outcome::Looped;
+            }
+
+            let Some(pos) = self.find_blocking(state, state.player, direction) else {
+                return if moved { Outcome::Stopped } else { Outcome::Unmoved };
+            };
+
+            moved = true;
+            match self.hit(state, pos, direction, record) {
+                Hit::Continue(next) => direction = next,
+                Hit::Done(Outcome::Fell) => {
+                    *state = before;
+                    return Outcome::Fell;
+                }
+                Hit::Done(outcome) => return outcome,
+            }
+        }
+    }
+
+
+    pub fn place(&self, state: &mut BoardState, item_index: usize, pos: GridPos) -> Outcome {
+        let page = &self.pages[state.page_index];
+        let Some((tag, properties)) = page.items.get(item_index).copied() else { return Outcome::Rejected; };
+
+        if state.goaled || state.is_item_used(state.page_index, item_index) || !page.is_floor(pos) {
+            return Outcome::Rejected;
+        }
+
+        state.used_items.insert((state.page_index, item_index));
+        state.placed.push((pos, tag, properties));
+        Outcome::Placed
+    }
+
+
+    /// Collidable gimmicks at `pos` on the current page, topmost first.
+    fn colliders(&self, state: &BoardState, pos: GridPos) -> Vec<Collider> {
+        let page = &self.pages[state.page_index];
+        let placed = state
+            .placed
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, (placed_pos, tag, _))| *placed_pos == pos && collide(*tag).is_some())
+            .map(|(index, (_, tag, properties))| Collider { tag: *tag, properties: *properties, source: Source::Placed(index) });
+
+        let ice_box = state
+            .ice_boxes
+            .contains(&(state.page_index, pos))
+            .then(|| Collider { tag: GimmickTag::IceBox, properties: page.properties(pos, GimmickTag::IceBox), source: Source::IceBox });
+
+        let tags = page
+            .tags(pos)
+            .iter()
+            .rev()
+            .filter(|tag| **tag != GimmickTag::IceBox)
+            .filter(|tag| collide(**tag).is_some() && !state.is_removed(state.page_index, pos, **tag))
+            .map(|tag| Collider { tag: *tag, properties: page.properties(pos, *tag), source: Source::Cell });
+
+        placed.chain(ice_box).chain(tags).collect()
+    }
+
+
+    /// Colliders the player runs into at `pos` when moving in `direction`, topmost first.
+    fn blocking(&self, state: &BoardState, pos: GridPos, direction: MoveDirection) -> Vec<Collider> {
+        self
+            .colliders(state, pos)
+            .into_iter()
+            .filter(|collider| !collider.passes(direction))
+            .collect()
+    }
+
+
+    fn find_blocking(&self, state: &BoardState, from: GridPos, direction: MoveDirection) -> Option<GridPos> {
+        let page = &self.pages[state.page_index];
+        let mut pos = from.neighbor(direction);
+
+        while page.contains(pos) {
+            if !self.blocking(state, pos, direction).is_empty() {
+                return Some(pos);
+            }
+            pos = pos.neighbor(direction);
+        }
+
+        None
+    }
+
+
+    /// Ice boxes stop in front of anything collidable, one-way tiles included.
+    fn find_collider(&self, state: &BoardState, from: GridPos, direction: MoveDirection) -> Option<GridPos> {
+        let page = &self.pages[state.page_index];
+        let mut pos = from.neighbor(direction);
+
+        while page.contains(pos) {
+            if !self.colliders(state, pos).is_empty() {
+                return Some(pos);
+            }
+            pos = pos.neighbor(direction);
+        }
+
+        None
+    }
+
+
+    fn hit(&self, state: &mut BoardState, pos: GridPos, direction: MoveDirection, record: &mut impl FnMut(Motion)) -> Hit {
+        let Some(collider) = self.blocking(state, pos, direction).first().copied() else { return Hit::Continue(direction); };
+        let Some((gimmick_collide, landing)) = collide(collider.tag) else { return Hit::Continue(direction); };
+
+        state.player = match landing {
+            Landing::InFront => pos.neighbor(direction.reverse()),
+            Landing::OnTop | Landing::Into => pos,
+        };
+        record(Motion::Slide { to: state.player, direction, landing });
+
+        match gimmick_collide {
+            GimmickCollide::StopMove => Hit::Done(Outcome::Stopped),
+            GimmickCollide::IceBox => {
+                if let Some(to) = self.slide(state, pos, collider, direction).filter(|to| *to != pos) {
+                    record(Motion::SlideIceBox { from: pos, to });
+                }
+                Hit::Done(Outcome::Stopped)
+            }
+            GimmickCollide::Goal => {
+                state.goaled = true;
+                Hit::Done(Outcome::Goaled)
+            }
+            GimmickCollide::NextPage => {
+                if state.page_index + 1 < self.pages.len() {
+                    state.page_index += 1;
+                }
+                record(Motion::NextPage);
+                Hit::Done(Outcome::NextPage)
+            }
+            GimmickCollide::Hole => Hit::Done(Outcome::Fell),
+            GimmickCollide::Teleport => match self.teleport_partner(state, pos, collider.properties.channel.unwrap_or_default()) {
+                Some(partner) => {
+                    state.player = partner;
+                    record(Motion::Teleport(partner));
+                    Hit::Continue(direction)
+                }
+                None => Hit::Done(Outcome::Stopped),
+            },
+            GimmickCollide::Portal => match collider.properties.target.filter(|target| target.page < self.pages.len()) {
+                Some(target) => {
+                    state.page_index = target.page;
+                    state.player = GridPos::from_cell(&target.cell());
+                    record(Motion::Portal { page_index: target.page, to: state.player });
+                    Hit::Done(Outcome::NextPage)
+                }
+                None => Hit::Done(Outcome::Stopped),
+            },
+            GimmickCollide::Arrow => {
+                let next = collider.properties.rotation.unwrap_or(MoveDirection::Up);
+                record(Motion::Turn(next));
+                Hit::Continue(next)
+            }
+            GimmickCollide::Turn => {
+                let next = collider.properties.rotation.unwrap_or(MoveDirection::Up).turn(direction);
+                record(Motion::Turn(next));
+                Hit::Continue(next)
+            }
+            GimmickCollide::Key => {
+                let channel = collider.properties.channel.unwrap_or_default();
+                self.remove(state, pos, collider);
+                *state.keys.entry(channel).or_default() += 1;
+                record(Motion::PickKey { pos, channel });
+                Hit::Continue(direction)
+            }
+            GimmickCollide::Lock => {
+                let channel = collider.properties.channel.unwrap_or_default();
+                let require_keys = collider.properties.require_keys.unwrap_or(1);
+                let keys = state.keys(channel);
+                if require_keys <= keys {
+                    // Drop empty counters so equal states hash equally.
+                    if keys == require_keys {
+                        state.keys.remove(&channel);
+                    } else {
+                        state.keys.insert(channel, keys - require_keys);
+                    }
+                    self.remove(state, pos, collider);
+                    record(Motion::Unlock { pos, channel, keys: require_keys });
+                    self.hit(state, pos, direction, record)
+                } else {
+                    Hit::Continue(direction)
+                }
+            }
+        }
+    }
+
+
+    /// The other teleporter of the same channel on the current page, placed ones included.
+    fn teleport_partner(&self, state: &BoardState, pos: GridPos, channel: u8) -> Option<GridPos> {
+        let page = &self.pages[state.page_index];
+        page
+            .cells()
+            .filter(|(_, tags)| tags.contains(&GimmickTag::Teleporter))
+            .map(|(cell_pos, _)| (*cell_pos, page.properties(*cell_pos, GimmickTag::Teleporter)))
+            .chain(state
+                .placed
+                .iter()
+                .filter(|(_, tag, _)| *tag == GimmickTag::Teleporter)
+                .map(|(placed_pos, _, properties)| (*placed_pos, *properties)))
+            .find(|(partner, properties)| *partner != pos && properties.channel.unwrap_or_default() == channel)
+            .map(|(partner, _)| partner)
+    }
+
+
+    /// Slides the ice box at `pos` until it rests in front of the next collider, returning where it ends up.
+    fn slide(&self, state: &mut BoardState, pos: GridPos, collider: Collider, direction: MoveDirection) -> Option<GridPos> {
+        let end = self
+            .find_collider(state, pos, direction)
+            .map(|collide_pos| collide_pos.neighbor(direction.reverse()))?;
+
+        match collider.source {
+            Source::Placed(index) => state.placed[index].0 = end,
+            Source::IceBox => {
+                state.ice_boxes.remove(&(state.page_index, pos));
+                state.ice_boxes.insert((state.page_index, end));
+            }
+            Source::Cell => {}
+        }
+        Some(end)
+    }
+
+
+    fn remove(&self, state: &mut BoardState, pos: GridPos, collider: Collider) {
+        match collider.source {
+            Source::Placed(index) => {
+                state.placed.remove(index);
+            }
+            Source::IceBox => {
+                state.ice_boxes.remove(&(state.page_index, pos));
+            }
+            Source::Cell => {
+                state.removed.insert((state.page_index, pos, collider.tag));
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::{StageLoadable, StageLoader};
+    use crate::loader::json::{GimmickProperties, PortalTarget};
+    use crate::simulation::board::{Board, Landing};
+    use crate::simulation::grid::GridPos;
+    use crate::simulation::motion::Motion;
+    use crate::simulation::outcome::Outcome;
+    use crate::simulation::tests::{page, rotate, stage};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+
+    #[test]
+    fn stop_in_front_of_wall() {
+        let board = Board::new(&stage(vec![page(&[
+            "#####",
+            "#P..#",
+            "#####",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(3, 1));
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(3, 1));
+    }
+
+
+    #[test]
+    fn stop_on_top_of_stop() {
+        let board = Board::new(&stage(vec![page(&[
+            "######",
+            "#P.S.#",
+            "######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(3, 1));
+    }
+
+
+    #[test]
+    fn unmoved_without_colliders() {
+        let board = Board::new(&stage(vec![page(&[
+            "P..",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Unmoved);
+        assert_eq!(state.player, GridPos::new(0, 0));
+    }
+
+
+    #[test]
+    fn reach_goal() {
+        let board = Board::new(&stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Goaled);
+        assert!(state.goaled);
+    }
+
+
+    #[test]
+    fn turn_redirects_player() {
+        let board = Board::new(&stage(vec![page(&[
+            "#####",
+            "#...#",
+            "#P.T#",
+            "#####",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(3, 1));
+    }
+
+
+    #[test]
+    fn key_opens_lock_over_goal() {
+        let board = Board::new(&stage(vec![page(&[
+            "#######",
+            "#PK.L.#",
+            "#######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Goaled);
+        assert_eq!(state.keys(0), 0);
+    }
+
+
+    #[test]
+    fn trace_key_and_lock() {
+        let board = Board::new(&stage(vec![page(&[
+            "#######",
+            "#PK.L.#",
+            "#######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+        let mut motions = Vec::new();
+
+        assert_eq!(board.trace(&mut state, MoveDirection::Right, &mut |motion| motions.push(motion)), Outcome::Goaled);
+        let slide = |x| Motion::Slide { to: GridPos::new(x, 1), direction: MoveDirection::Right, landing: Landing::OnTop };
+        assert_eq!(motions, vec![
+            slide(2),
+            Motion::PickKey { pos: GridPos::new(2, 1), channel: 0 },
+            slide(4),
+            Motion::Unlock { pos: GridPos::new(4, 1), channel: 0, keys: 1 },
+            slide(4),
+        ]);
+    }
+
+
+    #[test]
+    fn lock_without_key_is_passed_over() {
+        let board = Board::new(&stage(vec![page(&[
+            "######",
+            "#P.L.#",
+            "######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(4, 1));
+    }
+
+
+    #[test]
+    fn next_page_carries_player() {
+        let board = Board::new(&stage(vec![
+            page(&[
+                "#####",
+                "#P.N#",
+                "#####",
+            ], &[]),
+            page(&[
+                "#####",
+                "#G..#",
+                "#####",
+            ], &[]),
+        ])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::NextPage);
+        assert_eq!(state.page_index, 1);
+        assert_eq!(board.step(&mut state, MoveDirection::Left), Outcome::Goaled);
+    }
+
+
+    #[test]
+    fn placed_item_stops_player() {
+        let board = Board::new(&stage(vec![page(&[
+            "######",
+            "#P...#",
+            "######",
+        ], &[GimmickTag::Rock])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.place(&mut state, 0, GridPos::new(3, 1)), Outcome::Placed);
+        assert_eq!(board.place(&mut state, 0, GridPos::new(4, 1)), Outcome::Rejected);
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(2, 1));
+    }
+
+
+    #[test]
+    fn reject_item_on_wall() {
+        let board = Board::new(&stage(vec![page(&[
+            "####",
+            "#P.#",
+            "####",
+        ], &[GimmickTag::Rock])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.place(&mut state, 0, GridPos::new(0, 0)), Outcome::Rejected);
+    }
+
+
+    #[test]
+    fn ice_box_slides_until_collider() {
+        let board = Board::new(&stage(vec![page(&[
+            "#######",
+            "#P.I..#",
+            "#######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(2, 1));
+        assert_eq!(state.ice_boxes().copied().collect::<Vec<_>>(), vec![(0, GridPos::new(5, 1))]);
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(4, 1));
+    }
+
+
+    #[test]
+    fn blocked_ice_box_stays() {
+        let board = Board::new(&stage(vec![page(&[
+            "#####",
+            "#P.I#",
+            "#####",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(2, 1));
+        assert_eq!(state.ice_boxes().copied().collect::<Vec<_>>(), vec![(0, GridPos::new(3, 1))]);
+    }
+
+
+    #[test]
+    fn lock_requires_keys_from_properties() {
+        let mut page = page(&[
+            "#######",
+            "#PK.L.#",
+            "#######",
+        ], &[]);
+        let lock = page.cells.iter_mut().find(|cell| cell.tags.contains(&GimmickTag::Lock)).unwrap();
+        lock.properties.insert(GimmickTag::Lock, GimmickProperties { require_keys: Some(2), ..Default::default() });
+        let board = Board::new(&stage(vec![page])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(5, 1));
+        assert_eq!(state.keys(0), 1);
+    }
+
+
+    #[test]
+    fn lock_opens_only_with_matching_color() {
+        let mut page = page(&[
+            "#######",
+            "#PK.L.#",
+            "#######",
+        ], &[]);
+        let lock = page.cells.iter_mut().find(|cell| cell.tags.contains(&GimmickTag::Lock)).unwrap();
+        lock.properties.insert(GimmickTag::Lock, GimmickProperties { channel: Some(1), ..Default::default() });
+        let board = Board::new(&stage(vec![page])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.keys(0), 1);
+        assert_eq!(state.keys(1), 0);
+    }
+
+
+    #[test]
+    fn carried_keys_and_items_count() {
+        let board = Board::new(&stage(vec![page(&[
+            "########",
+            "#P..L..#",
+            "########",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+        state.carry([(0, 1)], [(GridPos::new(6, 1), GimmickTag::Rock, GimmickProperties::default())]);
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Goaled);
+        assert_eq!(state.keys(0), 0);
+        assert_eq!(state.placed().len(), 1);
+    }
+
+
+    #[test]
+    fn fall_into_hole_takes_move_back() {
+        let board = Board::new(&stage(vec![page(&[
+            "#######",
+            "#PK.O.#",
+            "#######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Fell);
+        assert_eq!(state, board.initial_state());
+    }
+
+
+    #[test]
+    fn teleport_keeps_sliding_from_partner() {
+        let board = Board::new(&stage(vec![page(&[
+            "#######",
+            "#PX...#",
+            "#######",
+            "#..X.G#",
+            "#######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Goaled);
+        assert_eq!(state.player, GridPos::new(5, 3));
+    }
+
+
+    #[test]
+    fn stop_on_unpaired_teleporter() {
+        let board = Board::new(&stage(vec![page(&[
+            "######",
+            "#PX..#",
+            "######",
+        ], &[])])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(2, 1));
+    }
+
+
+    #[test]
+    fn portal_to_earlier_page() {
+        let first = page(&[
+            "#####",
+            "#..G#",
+            "#####",
+        ], &[]);
+        let mut second = page(&[
+            "#####",
+            "#P..#",
+            "#####",
+        ], &[]);
+        let portal = second.cells.iter_mut().find(|cell| (cell.col, cell.row) == (3, 1)).unwrap();
+        portal.tags.push(GimmickTag::Portal);
+        portal.properties.insert(GimmickTag::Portal, GimmickProperties { target: Some(PortalTarget { page: 0, col: 1, row: 1 }), ..Default::default() });
+        let board = Board::new(&stage(vec![first, second])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::NextPage);
+        assert_eq!((state.page_index, state.player), (0, GridPos::new(1, 1)));
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Goaled);
+    }
+
+
+    #[test]
+    fn arrow_forces_direction_and_passes_along() {
+        let mut page = page(&[
+            "######",
+            "#P.A.#",
+            "#..A.#",
+            "#..G.#",
+            "######",
+        ], &[]);
+        rotate(&mut page, (3, 1), MoveDirection::Down);
+        rotate(&mut page, (3, 2), MoveDirection::Down);
+        let board = Board::new(&stage(vec![page])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Goaled);
+        assert_eq!(state.player, GridPos::new(3, 3));
+    }
+
+
+    #[test]
+    fn gate_blocks_other_sides() {
+        let mut page = page(&[
+            "######",
+            "#P.=.#",
+            "#..=.#",
+            "######",
+        ], &[]);
+        rotate(&mut page, (3, 1), MoveDirection::Right);
+        rotate(&mut page, (3, 2), MoveDirection::Right);
+        let board = Board::new(&stage(vec![page])).unwrap();
+        let mut state = board.initial_state();
+
+        assert_eq!(board.step(&mut state, MoveDirection::Right), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(4, 1));
+        assert_eq!(board.step(&mut state, MoveDirection::Down), Outcome::Stopped);
+        assert_eq!(board.step(&mut state, MoveDirection::Left), Outcome::Stopped);
+        assert_eq!(state.player, GridPos::new(4, 2));
+    }
+
+
+    #[test]
+    fn missing_player() {
+        assert!(Board::new(&stage(vec![page(&["#.#"], &[])])).is_err());
+    }
+
+
+    #[test]
+    fn build_built_in_stages() {
+        for stage in StageLoader::built_in().load().unwrap().into_iter().map(Result::unwrap) {
+            assert!(Board::new(&stage).is_ok(), "{}", stage.name);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::math::Vec2;
+
+use crate::loader::json::StageCell;
+use crate::stage::playing::gimmick::{GIMMICK_HEIGHT, GIMMICK_WIDTH};
+use crate::stage::playing::move_direction::MoveDirection;
+
+/// Column and row of a cell inside a page; rows grow downwards like the stage files.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct GridPos {
+    pub x: i32,
+    pub y: i32,
+}
+
+
+impl GridPos {
+    #[inline]
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+
+    #[inline]
+    pub fn from_cell(cell: &StageCell) -> Self {
+        Self::new(i32::from(cell.col), i32::from(cell.row))
+    }
+
+
+    /// The cell under a position inside a page's field, like `StageCell::from_pixel` but without clamping to `u8`.
+    #[inline]
+    pub fn from_pixel(pos: Vec2) -> Self {
+        Self::new((pos.x / GIMMICK_WIDTH).round() as i32, (-pos.y / GIMMICK_HEIGHT).round() as i32)
+    }
+
+
+    #[inline]
+    pub fn pixel(&self) -> Vec2 {
+        Vec2::new(self.x as f32 * GIMMICK_WIDTH, -(self.y as f32) * GIMMICK_HEIGHT)
+    }
+
+
+    #[inline]
+    pub const fn neighbor(&self, direction: MoveDirection) -> Self {
+        match direction {
+            MoveDirection::Left => Self::new(self.x - 1, self.y),
+            MoveDirection::Right => Self::new(self.x + 1, self.y),
+            MoveDirection::Up => Self::new(self.x, self.y - 1),
+            MoveDirection::Down => Self::new(self.x, self.y + 1),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::json::StageCell;
+    use crate::simulation::grid::GridPos;
+    use crate::stage::playing::move_direction::MoveDirection;
+
+    #[test]
+    fn neighbor_up_decrements_row() {
+        assert_eq!(GridPos::new(3, 3).neighbor(MoveDirection::Up), GridPos::new(3, 2));
+        assert_eq!(GridPos::new(3, 3).neighbor(MoveDirection::Left), GridPos::new(2, 3));
+    }
+
+
+    #[test]
+    fn pixel_matches_stage_cell() {
+        let cell = StageCell::new(3, 5, Vec::new());
+        assert_eq!(GridPos::new(3, 5).pixel(), cell.pixel());
+        assert_eq!(GridPos::from_pixel(cell.pixel()), GridPos::from_cell(&cell));
+        assert_eq!(GridPos::from_pixel(GridPos::new(-1, 300).pixel()), GridPos::new(-1, 300));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,46 @@
+use crate::simulation::board::Landing;
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::move_direction::MoveDirection;
+
+/// One thing that happens during a move, in the order `Board::trace` works it out.
+///
+/// The stage plays these back one after another; it decides nothing about the move itself.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Motion {
+    /// The player slides in `direction` and comes to rest on `to`.
+    Slide {
+        to: GridPos,
+        direction: MoveDirection,
+        landing: Landing,
+    },
+
+    /// The player faces `direction` from here on, sent by a turn tile or an arrow.
+    Turn(MoveDirection),
+
+    PickKey {
+        pos: GridPos,
+        channel: u8,
+    },
+
+    Unlock {
+        pos: GridPos,
+        channel: u8,
+        keys: usize,
+    },
+
+    /// Only recorded when the ice box actually moves.
+    SlideIceBox {
+        from: GridPos,
+        to: GridPos,
+    },
+
+    /// The player is put on the partner teleporter at this cell.
+    Teleport(GridPos),
+
+    Portal {
+        page_index: usize,
+        to: GridPos,
+    },
+
+    NextPage,
+}
\ No newline at end of file
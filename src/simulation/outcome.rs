@@ -0,0 +1,31 @@
+/// Result of a single player action on the board.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Outcome {
+    /// Nothing collidable lies in that direction, so the player stays put.
+    Unmoved,
+
+    Stopped,
+
+    NextPage,
+
+    Goaled,
+
+    /// The player keeps sliding through the same tiles forever (e.g. facing turn tiles).
+    Looped,
+
+    Placed,
+
+    /// The item placement was not allowed.
+    Rejected,
+
+    /// The player slid into a hole; the move is taken back, so the state is unchanged.
+    Fell,
+}
+
+
+impl Outcome {
+    #[inline]
+    pub const fn changed(&self) -> bool {
+        !matches!(self, Outcome::Unmoved | Outcome::Rejected | Outcome::Fell)
+    }
+}
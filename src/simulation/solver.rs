@@ -0,0 +1,173 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::simulation::action::Action;
+use crate::simulation::board::{Board, BoardState};
+use crate::simulation::outcome::Outcome;
+
+/// Upper bound of distinct states explored before the solver gives up.
+pub const MAX_STATES: usize = 500_000;
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Solution {
+    pub actions: Vec<Action>,
+}
+
+
+impl Solution {
+    /// The number of actions, moves and placements alike, in the shortest solution.
+    #[inline]
+    pub fn par(&self) -> usize {
+        self.actions.len()
+    }
+
+
+    #[inline]
+    pub fn moves(&self) -> usize {
+        self
+            .actions
+            .iter()
+            .filter(|action| matches!(action, Action::Move(_)))
+            .count()
+    }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solve {
+    Solved(Solution),
+
+    Unsolvable,
+
+    /// More than `MAX_STATES` states were reachable without finding the goal.
+    GaveUp,
+}
+
+
+impl Solve {
+    #[inline]
+    pub fn solution(self) -> Option<Solution> {
+        match self {
+            Solve::Solved(solution) => Some(solution),
+            _ => None,
+        }
+    }
+}
+
+
+/// Breadth-first search over board states, so the first goal found uses the fewest actions.
+pub fn solve(board: &Board, start: &BoardState) -> Solve {
+    let mut nodes: Vec<(BoardState, Option<(usize, Action)>)> = vec![(start.clone(), None)];
+    let mut visited = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([0]);
+
+    while let Some(index) = queue.pop_front() {
+        if nodes[index].0.goaled {
+            return Solve::Solved(Solution { actions: trace(&nodes, index) });
+        }
+
+        for action in board.actions(&nodes[index].0) {
+            let mut state = nodes[index].0.clone();
+            if matches!(board.apply(&mut state, action), Outcome::Unmoved | Outcome::Rejected | Outcome::Looped | Outcome::Fell) {
+                continue;
+            }
+            if !visited.insert(state.clone()) {
+                continue;
+            }
+            if MAX_STATES < visited.len() {
+                return Solve::GaveUp;
+            }
+
+            nodes.push((state, Some((index, action))));
+            queue.push_back(nodes.len() - 1);
+        }
+    }
+
+    Solve::Unsolvable
+}
+
+
+fn trace(nodes: &[(BoardState, Option<(usize, Action)>)], mut index: usize) -> Vec<Action> {
+    let mut actions = Vec::new();
+    while let Some((parent, action)) = nodes[index].1 {
+        actions.push(action);
+        index = parent;
+    }
+    actions.reverse();
+    actions
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::{StageLoadable, StageLoader};
+    use crate::simulation::action::Action;
+    use crate::simulation::board::Board;
+    use crate::simulation::grid::GridPos;
+    use crate::simulation::solver::{solve, Solve};
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+
+    #[test]
+    fn shortest_moves() {
+        let board = Board::new(&stage(vec![page(&[
+            "######",
+            "#P...#",
+            "#..R.#",
+            "#...G#",
+            "######",
+        ], &[])])).unwrap();
+
+        let solution = solve(&board, &board.initial_state()).solution().unwrap();
+        assert_eq!(solution.actions, vec![
+            Action::Move(MoveDirection::Right),
+            Action::Move(MoveDirection::Down),
+        ]);
+        assert_eq!(solution.par(), 2);
+    }
+
+
+    #[test]
+    fn needs_item_placement() {
+        let board = Board::new(&stage(vec![page(&[
+            "#####",
+            "#P..#",
+            "##.##",
+            "##.##",
+            "##G##",
+            "#####",
+        ], &[GimmickTag::Rock])])).unwrap();
+
+        let solution = solve(&board, &board.initial_state()).solution().unwrap();
+        assert_eq!(solution.actions, vec![
+            Action::Place { item_index: 0, pos: GridPos::new(3, 1) },
+            Action::Move(MoveDirection::Right),
+            Action::Move(MoveDirection::Down),
+        ]);
+        assert_eq!(solution.moves(), 2);
+    }
+
+
+    #[test]
+    fn unsolvable() {
+        let board = Board::new(&stage(vec![page(&[
+            "#####",
+            "#P..#",
+            "#####",
+            "#G..#",
+            "#####",
+        ], &[])])).unwrap();
+
+        assert_eq!(solve(&board, &board.initial_state()), Solve::Unsolvable);
+    }
+
+
+    #[test]
+    fn built_in_stages_are_solvable() {
+        for stage in StageLoader::built_in().load().unwrap().into_iter().map(Result::unwrap) {
+            let board = Board::new(&stage).unwrap();
+            assert!(solve(&board, &board.initial_state()).solution().is_some(), "{}", stage.name);
+        }
+    }
+}
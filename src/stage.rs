@@ -1,7 +1,7 @@
 use bevy::app::{App, Plugin, PreUpdate, Update};
 use bevy::audio::AudioBundle;
 use bevy::input::Input;
-use bevy::prelude::{AssetServer, Commands, Condition, EventReader, in_state, IntoSystemConfigs, KeyCode, NextState, OnEnter, OnExit, PlaybackSettings, Query, Res, ResMut, resource_exists_and_changed, With};
+use bevy::prelude::{AssetServer, Commands, Condition, EventReader, in_state, IntoSystemConfigs, KeyCode, NextState, OnEnter, OnExit, PlaybackSettings, Query, Res, ResMut, resource_exists_and_changed, Time, With};
 use bevy::text::Text;
 use bevy_trait_query::imports::Component;
 use bevy_undo2::prelude::{AppUndoEx, UndoRequester};
@@ -13,16 +13,25 @@ use crate::gama_state::GameState;
 use crate::loader::json::StageJson;
 use crate::page::page_count::PageCount;
 use crate::page::page_index::PageIndex;
+use crate::stage::history::{ActionHistory, commit_move_system, redo_if_input_keycode};
+use crate::stage::playing::gimmick::key::KeyColor;
 use crate::stage::playing::phase::idle::UndoPlayerIdleEvent;
 use crate::stage::playing::phase::moving::key::KeyCounter;
-use crate::stage::playing::phase::moving::MoveEvent;
+use crate::stage::playing::phase::picked_item::PickItem;
 use crate::stage::playing::phase::moving::stop_move::StopMoveEvent;
 use crate::stage::playing::PlayingPlugin;
+use crate::stage::replay::{ReplayAction, ReplayPlugin, ReplayRecord};
+use crate::stage::results::StageResultsPlugin;
 use crate::stage::state::StageState;
+use crate::stage::stats::{count_move_system, StageStats};
 use crate::stage_edit::page::spawn_page;
 
 mod state;
+pub mod history;
 pub mod playing;
+pub mod replay;
+pub mod results;
+pub mod stats;
 mod ui;
 
 
@@ -33,33 +42,49 @@ pub struct StagePlugin;
 impl Plugin for StagePlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_plugins(PlayingPlugin)
+            .add_plugins((PlayingPlugin, StageResultsPlugin, ReplayPlugin))
             .add_state::<StageState>()
-            .add_event::<MoveEvent>()
             .add_event::<StopMoveEvent>()
             .add_undo_event::<UndoPlayerIdleEvent>()
             .init_resource::<PageIndex>()
             .init_resource::<PageCount>()
+            .init_resource::<StageStats>()
+            .init_resource::<ActionHistory>()
             .add_systems(OnEnter(GameState::Stage), setup)
+            .add_systems(OnEnter(GameState::StageRestart), restart_stage_system)
+            .add_systems(OnEnter(StageState::Moving), (
+                count_move_system,
+                commit_move_system
+            ).run_if(in_state(GameState::Stage)))
             .add_systems(OnExit(GameState::Stage), (
                 destroy_all,
                 reset_stage_state,
+                clear_undo_system,
             ))
             .add_systems(Update, (
-                undo_if_input_keycode
+                undo_if_input_keycode,
+                redo_if_input_keycode,
+                restart_if_input_keycode
             ).run_if(in_state(GameState::Stage).and_then(in_state(StageState::Idle))), )
             .add_systems(PreUpdate, (
                 undo_player_idle_event_system
             ).run_if(in_state(GameState::Stage)))
             .add_systems(Update, (
                 change_keys_count_system
-            ).run_if(in_state(GameState::Stage).and_then(resource_exists_and_changed::<KeyCounter>())));
+            ).run_if(in_state(GameState::Stage).and_then(resource_exists_and_changed::<KeyCounter>())))
+            .add_systems(Update, (
+                change_hints_text_system
+            ).run_if(in_state(GameState::Stage).and_then(resource_exists_and_changed::<StageStats>())));
     }
 }
 
 
 #[derive(Component)]
-struct KeysCountText;
+struct KeysCountText(KeyColor);
+
+
+#[derive(Component)]
+struct HintsText;
 
 
 fn setup(
@@ -67,30 +92,50 @@ fn setup(
     assets: Res<GimmickAssets>,
     stage: Res<StageJson>,
     fonts: Res<FontAssets>,
-    asset_server: Res<AssetServer>
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
 ) {
     commands.spawn(AudioBundle{
         source: asset_server.load("audio/stage_bgm.ogg"),
         settings: PlaybackSettings::LOOP
     });
+    commands.insert_resource(StageStats::new(time.elapsed()));
+    commands.insert_resource(ActionHistory::default());
+    commands.insert_resource(KeyCounter::default());
+    commands.insert_resource(PickItem::default());
     commands.insert_resource(PageIndex::new(0));
     commands.insert_resource(PageCount::new(stage.pages.len()));
+    spawn_pages(&mut commands, &stage, &assets);
+    ui::spawn_ui(&mut commands, &fonts, &stage);
+}
 
+
+fn spawn_pages(commands: &mut Commands, stage: &StageJson, assets: &GimmickAssets) {
     for (page_index, page) in stage.pages.iter().enumerate() {
         let page_index = PageIndex(page_index);
-        spawn_page(&mut commands, page, page_index, &assets);
+        spawn_page(commands, stage, page, page_index, assets);
     }
-    
-    ui::spawn_ui(&mut commands, &fonts);
 }
 
 
 fn change_keys_count_system(
-    mut key_count_text: Query<&mut Text, With<KeysCountText>>,
+    mut key_count_texts: Query<(&mut Text, &KeysCountText)>,
     key_count: Res<KeyCounter>,
 ) {
-    let mut text = key_count_text.single_mut();
-    text.sections[0].value = format!("Key: {}", **key_count);
+    for (mut text, KeysCountText(key_color)) in key_count_texts.iter_mut() {
+        text.sections[0].value = format!("Key: {}", key_count.count(*key_color));
+    }
+}
+
+
+fn change_hints_text_system(
+    mut hints_texts: Query<&mut Text, With<HintsText>>,
+    stats: Res<StageStats>,
+    stage: Res<StageJson>,
+) {
+    for mut text in hints_texts.iter_mut() {
+        text.sections[0].value = ui::hints_text(stage.hint_budget().saturating_sub(stats.hints));
+    }
 }
 
 
@@ -101,16 +146,44 @@ fn reset_stage_state(
 }
 
 
+/// Drops the undo entries of the attempt being left, whose entities `destroy_all` has just despawned.
+fn clear_undo_system(mut requester: UndoRequester) {
+    requester.clear();
+}
+
+
 fn undo_if_input_keycode(
     mut requester: UndoRequester,
+    mut stats: ResMut<StageStats>,
+    mut history: ResMut<ActionHistory>,
+    mut record: ReplayRecord,
     keycode: Res<Input<KeyCode>>,
 ) {
-    if keycode.just_pressed(KeyCode::R) {
+    if keycode.just_pressed(KeyCode::R) && history.undo() {
+        record.record(ReplayAction::Undo);
+        stats.undos += 1;
         requester.undo();
     }
 }
 
 
+fn restart_if_input_keycode(
+    mut state: ResMut<NextState<GameState>>,
+    keycode: Res<Input<KeyCode>>,
+) {
+    if keycode.just_pressed(KeyCode::Back) {
+        state.set(GameState::StageRestart);
+    }
+}
+
+
+/// Setting the state the game is already in does nothing, so restarts leave the stage for
+/// [`GameState::StageRestart`] and come straight back, running its `OnExit` and `OnEnter` systems.
+fn restart_stage_system(mut state: ResMut<NextState<GameState>>) {
+    state.set(GameState::Stage);
+}
+
+
 fn undo_player_idle_event_system(
     mut state: ResMut<NextState<StageState>>,
     mut er: EventReader<UndoPlayerIdleEvent>,
@@ -123,37 +196,181 @@ fn undo_player_idle_event_system(
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use bevy::app::{App, Startup};
+    use bevy::asset::AssetPlugin;
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::system::SystemState;
+    use bevy::hierarchy::HierarchyPlugin;
+    use bevy::input::Input;
+    use bevy::math::{Vec3, Vec3Swizzles};
+    use bevy::prelude::{Entity, KeyCode, MinimalPlugins, NextState, State, Transform, TransformPlugin, With};
+    use bevy::time::TimeUpdateStrategy;
+    use bevy_tweening::{Animator, TweeningPlugin};
+    use bevy_undo2::prelude::UndoRequester;
     use bevy_undo2::UndoPlugin;
 
+    use crate::assets::font::FontAssets;
     use crate::assets::gimmick::GimmickAssets;
+    use crate::assets::stage::BuiltInStages;
+    use crate::gama_state::GameState;
     use crate::loader::{StageLoadable, StageLoader};
+    use crate::loader::json::{ItemCell, StageCell, StageJson};
     use crate::page::page_count::PageCount;
     use crate::page::page_index::PageIndex;
+    use crate::progress::Progress;
+    use crate::simulation::action::Action;
+    use crate::simulation::grid::GridPos;
+    use crate::simulation::outcome::Outcome;
+    use crate::simulation::Simulation;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::history::ActionHistory;
+    use crate::stage::playing::gimmick::{Floor, GimmickItem};
+    use crate::stage::playing::gimmick::player::Player;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::playing::phase::moving::goaled::GoaledEvent;
+    use crate::stage::playing::phase::picked_item::SpawnGimmickEvent;
+    use crate::stage::replay::ReplayRecorder;
     use crate::stage::playing::phase::start_move::StartMoveEvent;
-    use crate::stage::setup;
+    use crate::stage::playing::runtime_board::RuntimeBoard;
+    use crate::stage::{setup, StagePlugin};
     use crate::stage::state::StageState;
+    use crate::stage::stats::StageStats;
+
+    /// A known solution for every built-in stage, found with the solver and kept
+    /// so that rule changes which break a stage show up as a failing test.
+    const GOLDEN_SOLUTIONS: &[(&str, &[Action])] = &[
+        ("stage1", &[R]),
+        ("stage2", &[R, U]),
+        ("stage3", &[R, D, L]),
+        ("stage4", &[L, D, R, U, R, D, L, U, L]),
+        ("stage5", &[D, L, U, R, U, L, U, R, D, R, U, L, D, L, U, R, U, L, D, R, D]),
+        ("stage6", &[R, U, L, D, R, D, L, L, U, R, U, L, D, R, D, L, U, R, D]),
+        ("stage7", &[U, R, Action::Place { item_index: 0, pos: GridPos::new(23, 6) }, D, L, U, R, D, L, U, L, D]),
+        ("stage8", &[D, Action::Place { item_index: 0, pos: GridPos::new(14, 12) }, R, U, R, D, L, D, R, D, L, U, U]),
+        ("stage9", &[R, R, D, L]),
+    ];
+
+    const L: Action = Action::Move(MoveDirection::Left);
+    const U: Action = Action::Move(MoveDirection::Up);
+    const R: Action = Action::Move(MoveDirection::Right);
+    const D: Action = Action::Move(MoveDirection::Down);
+
+    /// Frames a single action may take before the test gives up on it.
+    const MAX_ACTION_FRAMES: usize = 2000;
+
 
     pub(crate) fn new_playing_app() -> App {
         let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()));
         app.add_state::<StageState>();
         app.add_plugins(UndoPlugin);
         app.add_event::<StartMoveEvent>();
         app.insert_resource(GimmickAssets::default());
-        let stages = StageLoader::new().load().unwrap();
-        let stage = stages
-            .iter()
-            .find(|stage| {
-                stage.name == "test"
-            })
-            .unwrap();
+        app.init_resource::<FontAssets>();
+        app.init_resource::<Progress>();
+        app.init_resource::<StageStats>();
+        app.init_resource::<ActionHistory>();
+        app.init_resource::<ReplayRecorder>();
+        app.insert_resource(stage(vec![page(&["###", "#P#", "###"], &[])]));
 
-        app.insert_resource(stage.clone());
+        app
+    }
+
+
+    /// An app running the whole stage headless on `stage`, with time advancing
+    /// by a fixed step every frame so tweens finish deterministically.
+    pub(crate) fn new_stage_app(stage: StageJson) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), TransformPlugin, HierarchyPlugin, TweeningPlugin, UndoPlugin, StagePlugin));
+        app.add_state::<GameState>();
+        app.init_resource::<Input<KeyCode>>();
+        app.insert_resource(GimmickAssets::default());
+        app.init_resource::<FontAssets>();
+        app.init_resource::<Progress>();
+        app.insert_resource(BuiltInStages(Vec::new()));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(20)));
+        app.insert_resource(PageCount::new(stage.pages.len()));
+        app.insert_resource(stage);
+        app.world.resource_mut::<NextState<GameState>>().set(GameState::Stage);
+        app.update();
 
         app
     }
 
 
+    /// Holds `key` down for a single frame.
+    pub(crate) fn press(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.release(key);
+        input.clear();
+    }
+
+
+    pub(crate) fn player_translation(app: &mut App) -> Vec3 {
+        app.world.query_filtered::<&Transform, With<Player>>().single(&app.world).translation
+    }
+
+
+    /// Plays `action` like the player would, then steps until the stage is idle again.
+    /// Returns whether [`GoaledEvent`] fired in the meantime.
+    pub(crate) fn play(app: &mut App, reader: &mut ManualEventReader<GoaledEvent>, action: Action) -> bool {
+        match action {
+            Action::Move(direction) => {
+                app.world.resource_mut::<ActionHistory>().start_move(direction);
+                app.world.send_event(StartMoveEvent(direction));
+            }
+            Action::Place { item_index, pos } => {
+                let page_index = *app.world.resource::<PageIndex>();
+                let item_row = app.world.resource::<StageJson>().pages[page_index.0].items[item_index].row;
+                let (item, GimmickItem(tag)) = app
+                    .world
+                    .query::<(Entity, &Transform, &PageIndex, &GimmickItem)>()
+                    .iter(&app.world)
+                    .find(|(_, transform, idx, _)| **idx == page_index && ItemCell::row_from_pixel(transform.translation.y) == item_row)
+                    .map(|(entity, _, _, item)| (entity, *item))
+                    .unwrap();
+                let floor = app
+                    .world
+                    .query_filtered::<(&Transform, &PageIndex), With<Floor>>()
+                    .iter(&app.world)
+                    .filter(|(_, idx)| **idx == page_index)
+                    .map(|(transform, _)| transform.translation)
+                    .find(|translation| GridPos::from_cell(&StageCell::from_pixel(translation.xy(), Vec::new())) == pos)
+                    .unwrap();
+                app.world.send_event(SpawnGimmickEvent(floor, item, tag));
+            }
+        }
+
+        let mut goaled = false;
+        for frame in 0..MAX_ACTION_FRAMES {
+            app.update();
+            goaled |= reader.iter(app.world.resource()).next().is_some();
+
+            let idle = *app.world.resource::<State<StageState>>().get() == StageState::Idle;
+            let animating = app.world.query::<&Animator<Transform>>().iter(&app.world).any(|animator| animator.tweenable().progress() < 1.);
+            if goaled || (10 <= frame && idle && !animating) {
+                return goaled;
+            }
+        }
+        panic!("{action:?} did not finish within {MAX_ACTION_FRAMES} frames");
+    }
+
+
+    fn built_in_stage(name: &str) -> StageJson {
+        StageLoader::built_in()
+            .load()
+            .unwrap()
+            .into_iter()
+            .map(Result::unwrap)
+            .find(|stage| stage.name == name)
+            .unwrap()
+    }
+
+
     #[test]
     fn setup_resources() {
         let mut app = new_playing_app();
@@ -162,6 +379,117 @@ mod tests {
         app.update();
 
         assert_eq!(app.world.resource::<PageIndex>().0, 0);
-        assert_eq!(app.world.resource::<PageCount>().0, 0);
+        assert_eq!(app.world.resource::<PageCount>().0, 1);
+    }
+
+
+    #[test]
+    fn restart_rebuilds_stage_with_empty_history() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "#####",
+            "#P..#",
+            "#####",
+        ], &[])]));
+        let start = player_translation(&mut app);
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+        assert_ne!(player_translation(&mut app), start);
+
+        press(&mut app, KeyCode::Back);
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(*app.world.resource::<State<GameState>>().get(), GameState::Stage);
+        assert_eq!(player_translation(&mut app), start);
+        assert_eq!(*app.world.resource::<ActionHistory>(), ActionHistory::default());
+
+        press(&mut app, KeyCode::R);
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(player_translation(&mut app), start);
+
+        // Even past the history, nothing of the previous attempt is left to undo.
+        let mut requester = SystemState::<UndoRequester>::new(&mut app.world);
+        requester.get_mut(&mut app.world).undo();
+        requester.apply(&mut app.world);
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(player_translation(&mut app), start);
+    }
+
+
+    #[test]
+    fn every_built_in_stage_has_golden_solution() {
+        for stage in StageLoader::built_in().load().unwrap().into_iter().map(Result::unwrap) {
+            assert!(GOLDEN_SOLUTIONS.iter().any(|(name, _)| *name == stage.name), "{} has no golden solution", stage.name);
+        }
+    }
+
+
+    #[test]
+    fn golden_solutions_goal_in_simulation() {
+        for (name, actions) in GOLDEN_SOLUTIONS {
+            let mut simulation = Simulation::new(&built_in_stage(name)).unwrap();
+            let outcomes = actions
+                .iter()
+                .map(|action| match *action {
+                    Action::Move(direction) => simulation.step(direction),
+                    Action::Place { item_index, pos } => simulation.place(item_index, pos),
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(outcomes.last(), Some(&Outcome::Goaled), "{name}: {outcomes:?}");
+            assert!(!outcomes.contains(&Outcome::Rejected), "{name}: {outcomes:?}");
+        }
+    }
+
+
+    #[test]
+    fn golden_solutions_goal_in_app() {
+        for (name, actions) in GOLDEN_SOLUTIONS {
+            let mut app = new_stage_app(built_in_stage(name));
+            let mut reader = ManualEventReader::<GoaledEvent>::default();
+            let (last, rest) = actions.split_last().unwrap();
+
+            for action in rest {
+                assert!(!play(&mut app, &mut reader, *action), "{name}: goaled before {last:?}");
+            }
+            assert!(play(&mut app, &mut reader, *last), "{name}: {last:?} did not reach the goal");
+        }
+    }
+
+
+    /// The stage only plays back what `Board` works out; after every step of a
+    /// golden solution, the last one included, the entities must still read back
+    /// as the simulated state and only the last step may reach the goal.
+    #[test]
+    fn golden_solutions_agree_with_simulation() {
+        for (name, actions) in GOLDEN_SOLUTIONS {
+            let stage = built_in_stage(name);
+            let mut simulation = Simulation::new(&stage).unwrap();
+            let mut app = new_stage_app(stage);
+            let mut reader = ManualEventReader::<GoaledEvent>::default();
+            let mut runtime_board = SystemState::<RuntimeBoard>::new(&mut app.world);
+
+            for (i, action) in actions.iter().enumerate() {
+                let goaled = play(&mut app, &mut reader, *action);
+                let outcome = match *action {
+                    Action::Move(direction) => simulation.step(direction),
+                    Action::Place { item_index, pos } => simulation.place(item_index, pos),
+                };
+                let last = i == actions.len() - 1;
+                assert_eq!(outcome == Outcome::Goaled, last, "{name}: {action:?} gave {outcome:?}");
+                assert_eq!(goaled, last, "{name}: GoaledEvent after {action:?}");
+
+                let (_, state) = runtime_board.get(&app.world).board().unwrap();
+                let expected = simulation.state();
+                let player = GridPos::from_cell(&StageCell::from_pixel(player_translation(&mut app).xy(), Vec::new()));
+                assert_eq!((app.world.resource::<PageIndex>().0, player), (expected.page_index, expected.player), "{name}: after {action:?}");
+                assert_eq!((state.page_index, state.player), (expected.page_index, expected.player), "{name}: after {action:?}");
+                assert!((0..=u8::MAX).all(|channel| state.keys(channel) == expected.keys(channel)), "{name}: keys after {action:?}");
+                assert_eq!(state.placed().len(), expected.placed().len(), "{name}: placed after {action:?}");
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,152 @@
+use bevy::input::Input;
+use bevy::math::Vec3;
+use bevy::prelude::{Entity, EventWriter, KeyCode, Res, ResMut, Resource};
+
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::move_direction::MoveDirection;
+use crate::stage::playing::phase::picked_item::SpawnGimmickEvent;
+use crate::stage::playing::phase::start_move::StartMoveEvent;
+use crate::stage::replay::{ReplayAction, ReplayRecord};
+
+/// One step of undo history, replayed by redo through the same events the player would send.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayerAction {
+    Move(MoveDirection),
+    Place {
+        pos: Vec3,
+        item: Entity,
+        tag: GimmickTag,
+    },
+}
+
+
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct ActionHistory {
+    done: Vec<PlayerAction>,
+    undone: Vec<PlayerAction>,
+    /// A move that is only kept once the player actually starts moving.
+    pending: Option<PlayerAction>,
+    redoing: bool,
+}
+
+
+impl ActionHistory {
+    #[inline]
+    pub fn start_move(&mut self, direction: MoveDirection) {
+        self.pending = Some(PlayerAction::Move(direction));
+        self.redoing = false;
+    }
+
+
+    #[inline]
+    pub fn commit_move(&mut self) {
+        if let Some(action) = self.pending.take() {
+            self.push(action);
+        }
+    }
+
+
+    /// A new action clears the redo stack; one replayed by redo doesn't.
+    pub fn push(&mut self, action: PlayerAction) {
+        self.done.push(action);
+        if !std::mem::take(&mut self.redoing) {
+            self.undone.clear();
+        }
+    }
+
+
+    /// Returns false when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(action) = self.done.pop() else { return false; };
+        self.undone.push(action);
+        true
+    }
+
+
+    pub fn redo(&mut self) -> Option<PlayerAction> {
+        let action = self.undone.pop()?;
+        self.redoing = true;
+        if let PlayerAction::Move(_) = action {
+            self.pending = Some(action);
+        }
+        Some(action)
+    }
+}
+
+
+pub fn commit_move_system(mut history: ResMut<ActionHistory>) {
+    history.commit_move();
+}
+
+
+pub fn redo_if_input_keycode(
+    mut history: ResMut<ActionHistory>,
+    mut move_writer: EventWriter<StartMoveEvent>,
+    mut spawn_writer: EventWriter<SpawnGimmickEvent>,
+    mut record: ReplayRecord,
+    keycode: Res<Input<KeyCode>>,
+) {
+    if !keycode.just_pressed(KeyCode::Y) {
+        return;
+    }
+
+    match history.redo() {
+        Some(PlayerAction::Move(direction)) => {
+            record.record(ReplayAction::Move { direction });
+            move_writer.send(StartMoveEvent(direction));
+        }
+        Some(PlayerAction::Place { pos, item, tag }) => spawn_writer.send(SpawnGimmickEvent(pos, item, tag)),
+        None => {}
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::stage::history::{ActionHistory, PlayerAction};
+    use crate::stage::playing::move_direction::MoveDirection;
+
+    #[test]
+    fn redo_undone_moves_in_order() {
+        let mut history = ActionHistory::default();
+        for direction in [MoveDirection::Left, MoveDirection::Up] {
+            history.start_move(direction);
+            history.commit_move();
+        }
+        assert!(history.undo());
+        assert!(history.undo());
+        assert!(!history.undo());
+
+        assert_eq!(history.redo(), Some(PlayerAction::Move(MoveDirection::Left)));
+        history.commit_move();
+        assert_eq!(history.redo(), Some(PlayerAction::Move(MoveDirection::Up)));
+        history.commit_move();
+        assert_eq!(history.redo(), None);
+    }
+
+
+    #[test]
+    fn new_action_clears_redo() {
+        let mut history = ActionHistory::default();
+        history.start_move(MoveDirection::Left);
+        history.commit_move();
+        history.undo();
+
+        history.start_move(MoveDirection::Right);
+        history.commit_move();
+
+        assert_eq!(history.redo(), None);
+    }
+
+
+    #[test]
+    fn blocked_move_is_not_kept() {
+        let mut history = ActionHistory::default();
+        history.start_move(MoveDirection::Left);
+        history.start_move(MoveDirection::Down);
+        history.commit_move();
+        history.undo();
+
+        assert_eq!(history.redo(), Some(PlayerAction::Move(MoveDirection::Down)));
+    }
+}
@@ -1,9 +1,8 @@
 use bevy::app::{App, Plugin};
-use bevy_trait_query::RegisterExt;
 
 use phase::idle::PlayingIdlePlugin;
 
-use crate::stage::playing::move_position::{MovePosition, MoveToFront, MoveUp};
+use crate::stage::playing::phase::fail::PlayingFailPlugin;
 use crate::stage::playing::phase::moving::PlayingMovingPlugin;
 use crate::stage::playing::phase::next_page::PlayingNextPagePlugin;
 use crate::stage::playing::phase::picked_item::PlayingPickedItemPlugin;
@@ -13,7 +12,8 @@ pub mod phase;
 pub mod move_direction;
 pub mod gimmick;
 pub mod collide;
-mod move_position;
+pub mod runtime_board;
+pub mod solve_task;
 
 
 #[derive(Default, Clone)]
@@ -28,10 +28,9 @@ impl Plugin for PlayingPlugin {
                 PlayingStartMovePlugin,
                 PlayingMovingPlugin,
                 PlayingNextPagePlugin,
-                PlayingPickedItemPlugin
-            ))
-            .register_component_as::<dyn MovePosition, MoveToFront>()
-            .register_component_as::<dyn MovePosition, MoveUp>();
+                PlayingPickedItemPlugin,
+                PlayingFailPlugin
+            ));
     }
 }
 
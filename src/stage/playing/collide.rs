@@ -1,6 +1,5 @@
-use bevy::prelude::Component;
-
-#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+/// What hitting a gimmick does to the move; see `simulation::board::collide`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum GimmickCollide {
     StopMove,
     IceBox,
@@ -8,5 +7,9 @@ pub enum GimmickCollide {
     Goal,
     Turn,
     Key,
-    Lock
+    Lock,
+    Hole,
+    Teleport,
+    Portal,
+    Arrow
 }
\ No newline at end of file
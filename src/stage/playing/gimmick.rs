@@ -7,6 +7,7 @@ use bevy_tweening::lens::TransformPositionLens;
 
 use crate::stage::playing::gimmick::tag::GimmickTag;
 use crate::stage::playing::move_direction::MoveDirection;
+use crate::stage::playing::phase::moving::MOTION_CODE;
 
 pub mod floor;
 pub mod player;
@@ -21,6 +22,10 @@ pub mod core;
 pub mod turn;
 pub mod key;
 pub mod lock;
+pub mod hole;
+pub mod one_way;
+pub mod portal;
+pub mod teleporter;
 
 
 pub const GIMMICK_WIDTH: f32 = 24.;
@@ -61,7 +66,7 @@ pub(crate) fn move_linear(
     if player_transform.translation.xy().abs_diff_eq(end.xy(), 1.0) {
         tween_writer.send(TweenCompleted {
             entity: commands.id(),
-            user_data: 1,
+            user_data: MOTION_CODE,
         });
     } else {
         let start = player_transform.translation;
@@ -74,7 +79,7 @@ pub(crate) fn move_linear(
                 end,
             },
         )
-            .with_completed_event(1);
+            .with_completed_event(MOTION_CODE);
 
         commands.insert(Animator::new(tween));
     }
@@ -2,14 +2,12 @@ use std::borrow::Cow;
 use bevy::asset::Handle;
 use bevy::core::Name;
 use bevy::math::Vec3;
-use bevy::prelude::{Bundle, Component, Image, SpriteBundle};
+use bevy::prelude::{Bundle, Color, Image, SpriteBundle};
 use crate::button::{SpriteButton, SpriteInteraction};
 
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
 use crate::stage::playing::gimmick::{Gimmick, new_gimmick_sprite_bundle};
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MovePosition;
 
 #[derive(Bundle, Clone)]
 pub struct GimmickCoreBundle {
@@ -41,26 +39,11 @@ impl GimmickCoreBundle {
             tag
         }
     }
-}
-
-
-#[derive(Bundle, Clone)]
-pub struct GimmickCollideBundle<T: Component> {
-    collide: GimmickCollide,
-    move_position: T,
-}
 
 
-impl<T> GimmickCollideBundle<T>
-    where T: MovePosition + Default + Component
-{
     #[inline]
-    pub fn new(
-        collide: GimmickCollide,
-    ) -> Self {
-        Self {
-            move_position: T::default(),
-            collide,
-        }
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.sprite.sprite.color = color;
+        self
     }
-}
\ No newline at end of file
+}
@@ -4,10 +4,8 @@ use bevy_trait_query::imports::Component;
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveUp;
 
 #[derive(Component, Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Goaled;
@@ -16,7 +14,6 @@ pub struct Goaled;
 #[derive(Bundle, Clone)]
 pub struct GoalBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveUp>,
 }
 
 
@@ -29,7 +26,6 @@ impl GoalBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Goal", assets.goal.clone(), pos, page_index, GimmickTag::Goal),
-            collide: GimmickCollideBundle::new(GimmickCollide::Goal),
         }
     }
 }
@@ -1,17 +1,35 @@
 use bevy::math::Vec3;
-use bevy::prelude::Bundle;
+use bevy::prelude::{Bundle, Color, Component};
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveUp;
+
+/// Locks only open with keys of the same colour; stages without colours only use the default one.
+#[derive(Default, Debug, Component, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct KeyColor(pub u8);
+
+
+impl KeyColor {
+    #[inline]
+    pub fn color(&self) -> Color {
+        match self.0 {
+            0 => Color::WHITE,
+            1 => Color::rgb(0.95, 0.35, 0.35),
+            2 => Color::rgb(0.35, 0.55, 0.95),
+            3 => Color::rgb(0.4, 0.85, 0.4),
+            _ => Color::rgb(0.95, 0.85, 0.3),
+        }
+    }
+}
+
 
 #[derive(Bundle, Clone)]
 pub struct KeyBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveUp>,
+    color: KeyColor,
 }
 
 
@@ -24,7 +42,17 @@ impl KeyBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Key", assets.key.clone(), pos, page_index, GimmickTag::Key),
-            collide: GimmickCollideBundle::new(GimmickCollide::Key),
+            color: KeyColor::default(),
+        }
+    }
+
+
+    #[inline]
+    pub fn with_color(self, color: KeyColor) -> Self {
+        Self {
+            core: self.core.with_color(color.color()),
+            color,
+            ..self
         }
     }
 }
@@ -3,10 +3,9 @@ use bevy::prelude::{Bundle, Component};
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
+use crate::stage::playing::gimmick::key::KeyColor;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveUp;
 
 
 #[derive(Default, Debug, Component, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -18,8 +17,8 @@ pub struct RequireKeys(pub usize);
 #[derive(Bundle, Clone)]
 pub struct LockBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveUp>,
-    require_keys: RequireKeys
+    require_keys: RequireKeys,
+    color: KeyColor,
 }
 
 
@@ -32,8 +31,18 @@ impl LockBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Lock", assets.lock1.clone(), pos, page_index, GimmickTag::Lock),
-            collide: GimmickCollideBundle::new(GimmickCollide::Lock),
-            require_keys: RequireKeys(1)
+            require_keys: RequireKeys(1),
+            color: KeyColor::default(),
+        }
+    }
+
+
+    #[inline]
+    pub fn with_color(self, color: KeyColor) -> Self {
+        Self {
+            core: self.core.with_color(color.color()),
+            color,
+            ..self
         }
     }
 }
@@ -3,10 +3,8 @@ use bevy::prelude::{Bundle, Component};
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveUp;
 
 #[derive(Default, Debug, Copy, Clone, Component)]
 pub struct NextPageProcessing;
@@ -15,7 +13,6 @@ pub struct NextPageProcessing;
 #[derive(Bundle, Clone)]
 pub struct NextPageBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveUp>,
 }
 
 
@@ -28,7 +25,6 @@ impl NextPageBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("NextPage", assets.next_page.clone(), pos, page_index, GimmickTag::NextPage),
-            collide: GimmickCollideBundle::new(GimmickCollide::NextPage),
         }
     }
 }
@@ -0,0 +1,61 @@
+use bevy::math::Vec3;
+use bevy::prelude::{Bundle, Component};
+
+use crate::assets::gimmick::GimmickAssets;
+use crate::page::page_index::PageIndex;
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::move_direction::MoveDirection;
+
+/// The direction an arrow or gate faces; the player moving that way passes straight over it.
+#[derive(Debug, Component, Copy, Clone, Eq, PartialEq, Hash)]
+#[repr(transparent)]
+pub struct OneWay(pub MoveDirection);
+
+
+/// Sends the player on in the direction it faces, whichever way the player came from.
+#[derive(Bundle, Clone)]
+pub struct ArrowBundle {
+    core: GimmickCoreBundle,
+    one_way: OneWay,
+}
+
+
+impl ArrowBundle {
+    #[inline]
+    pub fn new(
+        assets: &GimmickAssets,
+        pos: Vec3,
+        page_index: PageIndex,
+        direction: MoveDirection,
+    ) -> Self {
+        Self {
+            core: GimmickCoreBundle::new("Arrow", assets.arrow.clone(), pos, page_index, GimmickTag::Arrow),
+            one_way: OneWay(direction),
+        }
+    }
+}
+
+
+/// Blocks the player like a wall unless entered in the direction it faces.
+#[derive(Bundle, Clone)]
+pub struct GateBundle {
+    core: GimmickCoreBundle,
+    one_way: OneWay,
+}
+
+
+impl GateBundle {
+    #[inline]
+    pub fn new(
+        assets: &GimmickAssets,
+        pos: Vec3,
+        page_index: PageIndex,
+        direction: MoveDirection,
+    ) -> Self {
+        Self {
+            core: GimmickCoreBundle::new("Gate", assets.gate.clone(), pos, page_index, GimmickTag::Gate),
+            one_way: OneWay(direction),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::math::Vec3;
+use bevy::prelude::Bundle;
+
+use crate::assets::gimmick::GimmickAssets;
+use crate::page::page_index::PageIndex;
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+
+/// Where the portal leads is read from its [`GimmickProperties`](crate::loader::json::GimmickProperties).
+#[derive(Bundle, Clone)]
+pub struct PortalBundle {
+    core: GimmickCoreBundle,
+}
+
+
+impl PortalBundle {
+    #[inline]
+    pub fn new(
+        assets: &GimmickAssets,
+        pos: Vec3,
+        page_index: PageIndex,
+    ) -> Self {
+        Self {
+            core: GimmickCoreBundle::new("Portal", assets.portal.clone(), pos, page_index, GimmickTag::Portal),
+        }
+    }
+}
@@ -3,15 +3,12 @@ use bevy::prelude::Bundle;
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveToFront;
 
 #[derive(Bundle, Clone)]
 pub struct RockBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveToFront>,
 }
 
 
@@ -24,7 +21,6 @@ impl RockBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Rock", assets.rock.clone(), pos, page_index, GimmickTag::Rock),
-            collide: GimmickCollideBundle::new(GimmickCollide::StopMove),
         }
     }
 }
@@ -3,15 +3,12 @@ use bevy::prelude::Bundle;
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveUp;
 
 #[derive(Bundle)]
 pub struct StopBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveUp>,
 }
 
 
@@ -24,7 +21,6 @@ impl StopBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Stop", assets.stop.clone(), pos, page_index, GimmickTag::Stop),
-            collide: GimmickCollideBundle::new(GimmickCollide::StopMove),
         }
     }
 }
\ No newline at end of file
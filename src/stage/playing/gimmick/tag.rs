@@ -1,26 +1,32 @@
 use bevy::asset::Handle;
 use bevy::ecs::system::EntityCommands;
 use bevy::math::Vec3;
-use bevy::prelude::{ChildBuilder, Commands, Image};
+use bevy::prelude::{ChildBuilder, Commands, Image, Transform};
 use bevy::ui::UiImage;
 use bevy_trait_query::imports::Component;
 use serde::{Deserialize, Serialize};
 
 use crate::assets::gimmick::GimmickAssets;
+use crate::loader::json::GimmickProperties;
 use crate::page::page_index::PageIndex;
 use crate::stage::playing::gimmick::floor::FloorBundle;
 use crate::stage::playing::gimmick::goal::GoalBundle;
+use crate::stage::playing::gimmick::hole::HoleBundle;
 use crate::stage::playing::gimmick::ice_box::IceBoxBundle;
-use crate::stage::playing::gimmick::key::KeyBundle;
-use crate::stage::playing::gimmick::lock::LockBundle;
+use crate::stage::playing::gimmick::key::{KeyBundle, KeyColor};
+use crate::stage::playing::gimmick::lock::{LockBundle, RequireKeys};
 use crate::stage::playing::gimmick::next_page::NextPageBundle;
+use crate::stage::playing::gimmick::one_way::{ArrowBundle, GateBundle};
 use crate::stage::playing::gimmick::player::PlayerBundle;
+use crate::stage::playing::gimmick::portal::PortalBundle;
 use crate::stage::playing::gimmick::rock::RockBundle;
 use crate::stage::playing::gimmick::stop::StopBundle;
+use crate::stage::playing::gimmick::teleporter::{TeleportChannel, TeleporterBundle};
 use crate::stage::playing::gimmick::turn::TurnBundle;
 use crate::stage::playing::gimmick::wall::WallBundle;
+use crate::stage::playing::move_direction::MoveDirection;
 
-#[derive(Eq, PartialEq, Debug, Copy, Clone, Deserialize, Serialize, Component, PartialOrd, Ord)]
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash, Deserialize, Serialize, Component, PartialOrd, Ord)]
 pub enum GimmickTag {
     Floor,
     NextPage,
@@ -34,11 +40,16 @@ pub enum GimmickTag {
     Turn,
     Key,
     Lock,
+    Hole,
+    Teleporter,
+    Portal,
+    Arrow,
+    Gate,
 }
 
 
 macro_rules! spawn_gimmick {
-    ($self: expr, $commands: expr, $assets: expr, $pos: expr, $page_index: expr) => {
+    ($self: expr, $commands: expr, $assets: expr, $pos: expr, $page_index: expr, $properties: expr) => {
         match $self {
             GimmickTag::Floor => $commands.spawn(FloorBundle::new($assets, $pos, $page_index)),
             GimmickTag::Wall => $commands.spawn(WallBundle::new($assets.wall.clone(), $pos, $page_index, GimmickTag::Wall)),
@@ -50,8 +61,13 @@ macro_rules! spawn_gimmick {
             GimmickTag::Stop => $commands.spawn(StopBundle::new($assets, $pos, $page_index)),
             GimmickTag::IceBox => $commands.spawn(IceBoxBundle::new($assets, $pos, $page_index)),
             GimmickTag::Turn => $commands.spawn(TurnBundle::new($assets, $pos, $page_index)),
-            GimmickTag::Key => $commands.spawn(KeyBundle::new($assets, $pos, $page_index)),
-            GimmickTag::Lock => $commands.spawn(LockBundle::new($assets, $pos, $page_index))
+            GimmickTag::Key => $commands.spawn(KeyBundle::new($assets, $pos, $page_index).with_color(KeyColor($properties.channel.unwrap_or_default()))),
+            GimmickTag::Lock => $commands.spawn(LockBundle::new($assets, $pos, $page_index).with_color(KeyColor($properties.channel.unwrap_or_default()))),
+            GimmickTag::Hole => $commands.spawn(HoleBundle::new($assets, $pos, $page_index)),
+            GimmickTag::Teleporter => $commands.spawn(TeleporterBundle::new($assets, $pos, $page_index, TeleportChannel($properties.channel.unwrap_or_default()))),
+            GimmickTag::Portal => $commands.spawn(PortalBundle::new($assets, $pos, $page_index)),
+            GimmickTag::Arrow => $commands.spawn(ArrowBundle::new($assets, $pos, $page_index, $properties.rotation.unwrap_or(MoveDirection::Up))),
+            GimmickTag::Gate => $commands.spawn(GateBundle::new($assets, $pos, $page_index, $properties.rotation.unwrap_or(MoveDirection::Up)))
         }
     };
 }
@@ -63,8 +79,11 @@ impl GimmickTag {
         assets: &GimmickAssets,
         pos: Vec3,
         page_index: PageIndex,
+        properties: GimmickProperties,
     ) -> EntityCommands<'w, 's, 'a> {
-        spawn_gimmick!(self, commands, assets, pos, page_index)
+        let mut entity = spawn_gimmick!(self, commands, assets, pos, page_index, properties);
+        insert_properties(&mut entity, pos, properties);
+        entity
     }
 
 
@@ -74,8 +93,10 @@ impl GimmickTag {
         assets: &GimmickAssets,
         pos: Vec3,
         page_index: PageIndex,
+        properties: GimmickProperties,
     )  {
-        spawn_gimmick!(self, commands, assets, pos, page_index);
+        let mut entity = spawn_gimmick!(self, commands, assets, pos, page_index, properties);
+        insert_properties(&mut entity, pos, properties);
     }
 
 
@@ -93,7 +114,12 @@ impl GimmickTag {
             GimmickTag::IceBox => assets.ice_box.clone(),
             GimmickTag::Turn => assets.turn.clone(),
             GimmickTag::Key => assets.key.clone(),
-            GimmickTag::Lock => assets.lock1.clone()
+            GimmickTag::Lock => assets.lock1.clone(),
+            GimmickTag::Hole => assets.hole.clone(),
+            GimmickTag::Teleporter => assets.teleporter.clone(),
+            GimmickTag::Portal => assets.portal.clone(),
+            GimmickTag::Arrow => assets.arrow.clone(),
+            GimmickTag::Gate => assets.gate.clone()
         }
     }
 
@@ -117,4 +143,20 @@ impl GimmickTag {
     //         GimmickTag::Goal => "gimmick/goal.png".to_string()
     //     }
     // }
-}
\ No newline at end of file
+}
+
+
+/// Overrides the defaults of the spawned bundle and keeps the properties so the editor can save them back.
+fn insert_properties(entity: &mut EntityCommands, pos: Vec3, properties: GimmickProperties) {
+    if properties.is_empty() {
+        return;
+    }
+
+    entity.insert(properties);
+    if let Some(rotation) = properties.rotation {
+        entity.insert(Transform::from_translation(pos).with_rotation(rotation.quat()));
+    }
+    if let Some(require_keys) = properties.require_keys {
+        entity.insert(RequireKeys(require_keys));
+    }
+}
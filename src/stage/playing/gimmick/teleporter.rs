@@ -0,0 +1,38 @@
+use bevy::math::Vec3;
+use bevy::prelude::{Bundle, Component};
+
+use crate::assets::gimmick::GimmickAssets;
+use crate::page::page_index::PageIndex;
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
+use crate::stage::playing::gimmick::key::KeyColor;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+
+/// Teleporters on the same page and channel form a pair.
+#[derive(Default, Debug, Component, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct TeleportChannel(pub u8);
+
+
+#[derive(Bundle, Clone)]
+pub struct TeleporterBundle {
+    core: GimmickCoreBundle,
+    channel: TeleportChannel,
+}
+
+
+impl TeleporterBundle {
+    #[inline]
+    pub fn new(
+        assets: &GimmickAssets,
+        pos: Vec3,
+        page_index: PageIndex,
+        channel: TeleportChannel,
+    ) -> Self {
+        Self {
+            // Tinted like keys so the pairs can be told apart.
+            core: GimmickCoreBundle::new("Teleporter", assets.teleporter.clone(), pos, page_index, GimmickTag::Teleporter)
+                .with_color(KeyColor(channel.0).color()),
+            channel,
+        }
+    }
+}
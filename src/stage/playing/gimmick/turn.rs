@@ -3,15 +3,12 @@ use bevy::prelude::Bundle;
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::{ MoveUp};
 
 #[derive(Bundle, Clone)]
 pub struct TurnBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveUp>,
 }
 
 
@@ -24,7 +21,6 @@ impl TurnBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Turn", assets.turn.clone(), pos, page_index, GimmickTag::Turn),
-            collide: GimmickCollideBundle::new(GimmickCollide::Turn),
         }
     }
 }
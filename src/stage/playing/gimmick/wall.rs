@@ -3,15 +3,12 @@ use bevy::math::Vec3;
 use bevy::prelude::{Bundle, Image};
 
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::core::{GimmickCollideBundle, GimmickCoreBundle};
+use crate::stage::playing::gimmick::core::GimmickCoreBundle;
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage::playing::move_position::MoveToFront;
 
 #[derive(Bundle, Clone)]
 pub struct WallBundle {
     core: GimmickCoreBundle,
-    collide: GimmickCollideBundle<MoveToFront>,
 }
 
 
@@ -25,7 +22,6 @@ impl WallBundle {
     ) -> Self {
         Self {
             core: GimmickCoreBundle::new("Wall", texture, pos, page_index, tag),
-            collide: GimmickCollideBundle::new(GimmickCollide::StopMove),
         }
     }
 }
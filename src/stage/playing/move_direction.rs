@@ -3,11 +3,12 @@ use std::f32::consts::PI;
 use bevy::math::{Quat, Vec3};
 use bevy::prelude::{Resource, Transform};
 use bevy_trait_query::imports::Component;
+use serde::{Deserialize, Serialize};
 
 use crate::stage::playing::gimmick::GIMMICK_SIZE_VEC3;
 use crate::stage::playing::move_direction::MoveDirection::{Down, Left, Right, Up};
 
-#[derive(Component, Resource, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Component, Resource, Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum MoveDirection {
     Left,
     Right,
@@ -28,6 +29,17 @@ impl MoveDirection {
     }
 
 
+    #[inline]
+    pub fn clockwise(&self) -> Self {
+        match self {
+            MoveDirection::Up => MoveDirection::Right,
+            MoveDirection::Right => MoveDirection::Down,
+            MoveDirection::Down => MoveDirection::Left,
+            MoveDirection::Left => MoveDirection::Up,
+        }
+    }
+
+
     #[inline]
     pub fn vec3_unit(&self) -> Vec3 {
         match self {
@@ -9,6 +9,7 @@ pub mod start_move;
 pub mod next_page;
 pub mod picked_item;
 pub mod moving;
+pub mod fail;
 
 
 #[derive(SystemParam)]
@@ -20,10 +21,16 @@ pub struct FieldParams<'w, 's> {
 
 impl<'w, 's> FieldParams<'w, 's> {
     pub fn add_child(&self, commands: &mut Commands, child: Entity){
+        self.add_child_to(commands, *self.next_page_index, child);
+    }
+
+
+    /// Puts `child` on the field of the given page rather than the shown one.
+    pub fn add_child_to(&self, commands: &mut Commands, page_index: PageIndex, child: Entity){
         let (entity, _) = self
             .fields
             .iter()
-            .find(|(_, page_index)|**page_index == *self.next_page_index)
+            .find(|(_, idx)|**idx == page_index)
             .unwrap();
 
         commands.entity(entity).add_child(child);
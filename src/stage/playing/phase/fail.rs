@@ -0,0 +1,102 @@
+use bevy::app::{App, Plugin, Update};
+use bevy::math::Vec3;
+use bevy::prelude::{Commands, Entity, EventReader, in_state, IntoSystemConfigs, OnEnter, Query, ResMut, Transform, With};
+use bevy_tweening::{Animator, EaseMethod, Tween, TweenCompleted};
+use bevy_tweening::lens::TransformScaleLens;
+use bevy_undo2::prelude::UndoRequester;
+
+use crate::stage::history::ActionHistory;
+use crate::stage::playing::gimmick::player::Player;
+use crate::stage::state::StageState;
+
+const FALL_CODE: u64 = 52125;
+
+
+/// The player fell into a hole: plays the fall, then undoes the move that led there.
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct PlayingFailPlugin;
+
+
+impl Plugin for PlayingFailPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(StageState::Fail), fall_system)
+            .add_systems(Update, fall_completed_system.run_if(in_state(StageState::Fail)));
+    }
+}
+
+
+fn fall_system(
+    mut commands: Commands,
+    player: Query<(Entity, &Transform), With<Player>>,
+) {
+    let Ok((pe, pt)) = player.get_single() else { return; };
+
+    commands
+        .entity(pe)
+        .insert(Animator::new(fall_tween(pt.scale).with_completed_event(FALL_CODE)));
+}
+
+
+/// Not counted as an undo in the stats; the redo history keeps the move so it can be retried.
+fn fall_completed_system(
+    mut commands: Commands,
+    mut er: EventReader<TweenCompleted>,
+    mut requester: UndoRequester,
+    mut history: ResMut<ActionHistory>,
+    player: Query<Entity, With<Player>>,
+) {
+    for _ in er.iter().filter(|e| e.user_data == FALL_CODE) {
+        if let Ok(pe) = player.get_single() {
+            commands.entity(pe).remove::<Animator<Transform>>();
+        }
+        if history.undo() {
+            requester.undo();
+        }
+    }
+}
+
+
+#[inline]
+fn fall_tween(start: Vec3) -> Tween<Transform> {
+    Tween::new(
+        EaseMethod::Linear,
+        std::time::Duration::from_millis(400),
+        TransformScaleLens {
+            start,
+            end: Vec3::ZERO,
+        },
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::math::Vec3;
+    use bevy::prelude::{Transform, With};
+
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::history::{ActionHistory, PlayerAction};
+    use crate::stage::playing::gimmick::player::Player;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::tests::{new_stage_app, play};
+
+    #[test]
+    fn fall_undoes_move() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "#####",
+            "#P.O#",
+            "#####",
+        ], &[])]));
+        let start = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+
+        assert!(!play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right)));
+
+        let player = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+        assert_eq!(player.translation, start.translation);
+        assert_eq!(player.scale, Vec3::ONE);
+        assert!(matches!(app.world.resource_mut::<ActionHistory>().redo(), Some(PlayerAction::Move(MoveDirection::Right))));
+    }
+}
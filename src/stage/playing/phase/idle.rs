@@ -5,12 +5,17 @@ use bevy_egui::egui::ImageData::Color;
 use crate::button::SpriteInteraction;
 use crate::gama_state::GameState;
 use crate::page::page_index::PageIndex;
+use crate::stage::history::ActionHistory;
+use crate::stage::replay::{ReplayAction, ReplayRecord};
 use crate::stage::playing::gimmick::{GimmickItem, GimmickItemDisabled};
 use crate::stage::playing::move_direction::MoveDirection;
+use crate::stage::playing::phase::idle::hint::{clear_hint_system, hint_system, pulse_hint_system, show_hint_system};
 use crate::stage::playing::phase::picked_item::PickedItemEvent;
 use crate::stage::playing::phase::start_move::StartMoveEvent;
 use crate::stage::state::StageState;
 
+mod hint;
+
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Event)]
 pub struct UndoPlayerIdleEvent;
@@ -31,10 +36,16 @@ impl Plugin for PlayingIdlePlugin {
                     update_item_colors_system,
                     input_move_system,
                     picked_item_system,
-                    back_scene_system
+                    back_scene_system,
+                    hint_system
                 )
                     .run_if(in_state(GameState::Stage).and_then(in_state(StageState::Idle))),
-            );
+            )
+            .add_systems(Update, (
+                show_hint_system.run_if(in_state(GameState::Stage)),
+                pulse_hint_system.run_if(in_state(GameState::Stage)),
+                clear_hint_system.run_if(in_state(GameState::Stage).and_then(resource_exists_and_changed::<ActionHistory>()))
+            ));
     }
 }
 
@@ -51,9 +62,13 @@ fn back_scene_system(
 
 fn input_move_system(
     mut ew: EventWriter<StartMoveEvent>,
+    mut history: ResMut<ActionHistory>,
+    mut record: ReplayRecord,
     keys: Res<Input<KeyCode>>,
 ) {
     let mut emit = |direction: MoveDirection| {
+        history.start_move(direction);
+        record.record(ReplayAction::Move { direction });
         ew.send(StartMoveEvent(direction));
     };
 
@@ -0,0 +1,199 @@
+use bevy::hierarchy::DespawnRecursiveExt;
+use bevy::input::Input;
+use bevy::prelude::{Color, Commands, Component, default, Entity, KeyCode, Or, Query, Res, ResMut, Sprite, SpriteBundle, Time, Transform, With};
+
+use crate::assets::gimmick::GimmickAssets;
+use crate::loader::json::StageJson;
+use crate::simulation::action::Action;
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::GIMMICK_SIZE;
+use crate::stage::playing::phase::FieldParams;
+use crate::stage::playing::runtime_board::RuntimeBoard;
+use crate::stage::playing::solve_task::SolveTask;
+use crate::stage::stats::StageStats;
+
+/// Drawn above every gimmick of the field.
+const HINT_Z: f32 = 10.;
+
+
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Hint;
+
+
+/// A floor tile that fades in and out to show where to place an item.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HintPulse;
+
+
+/// The search for the next action, started from the player's cell when the hint was asked for.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct HintSearch {
+    player: GridPos,
+}
+
+
+pub fn hint_system(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    stage: Res<StageJson>,
+    stats: Res<StageStats>,
+    runtime_board: RuntimeBoard,
+    hints: Query<Entity, Or<(With<Hint>, With<HintSearch>)>>,
+) {
+    if !keys.just_pressed(KeyCode::H) || !hints.is_empty() || stage.hint_budget() <= stats.hints {
+        return;
+    }
+    let Ok((board, state)) = runtime_board.board() else { return; };
+    commands.spawn((HintSearch { player: state.player }, SolveTask::new(board, state)));
+}
+
+
+/// Shows the hint once its search has finished; only a hint that is shown is counted.
+pub fn show_hint_system(
+    mut commands: Commands,
+    mut stats: ResMut<StageStats>,
+    mut searches: Query<(Entity, &HintSearch, &mut SolveTask)>,
+    assets: Res<GimmickAssets>,
+    field_params: FieldParams,
+) {
+    for (search, HintSearch { player }, mut task) in searches.iter_mut() {
+        let Some(solve) = task.poll() else { continue; };
+        commands.entity(search).despawn();
+        let Some(action) = solve
+            .solution()
+            .and_then(|solution| solution.actions.first().copied()) else { continue; };
+        spawn_hint(&mut commands, &assets, &field_params, *player, action);
+        stats.hints += 1;
+    }
+}
+
+
+fn spawn_hint(commands: &mut Commands, assets: &GimmickAssets, field_params: &FieldParams, player: GridPos, action: Action) {
+    let hint = match action {
+        Action::Move(direction) => commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::YELLOW,
+                    custom_size: Some(GIMMICK_SIZE),
+                    ..default()
+                },
+                texture: assets.arrow.clone(),
+                transform: Transform::from_translation(player.neighbor(direction).pixel().extend(HINT_Z))
+                    .with_rotation(direction.quat()),
+                ..default()
+            })
+            .insert(Hint)
+            .id(),
+        Action::Place { pos, .. } => commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: Color::YELLOW,
+                    custom_size: Some(GIMMICK_SIZE),
+                    ..default()
+                },
+                transform: Transform::from_translation(pos.pixel().extend(HINT_Z)),
+                ..default()
+            })
+            .insert((Hint, HintPulse))
+            .id(),
+    };
+    field_params.add_child(commands, hint);
+}
+
+
+pub fn pulse_hint_system(
+    mut tiles: Query<&mut Sprite, With<HintPulse>>,
+    time: Res<Time>,
+) {
+    let alpha = 0.35 + 0.25 * (time.elapsed_seconds() * 4.).sin();
+    for mut sprite in tiles.iter_mut() {
+        sprite.color.set_a(alpha);
+    }
+}
+
+
+/// Removes the shown hint, or drops the search for one, as soon as the player acts or undoes.
+pub fn clear_hint_system(
+    mut commands: Commands,
+    hints: Query<Entity, Or<(With<Hint>, With<HintSearch>)>>,
+) {
+    for hint in hints.iter() {
+        commands.entity(hint).despawn_recursive();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::{KeyCode, Transform, With};
+    use bevy_undo2::prelude::UndoRequester;
+
+    use crate::loader::json::GimmickProperties;
+    use crate::simulation::action::Action;
+    use crate::simulation::grid::GridPos;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::playing::phase::idle::hint::{Hint, HintSearch};
+    use crate::stage::stats::StageStats;
+    use crate::stage::tests::{new_stage_app, play, press};
+
+    fn wait_for_hint(app: &mut App) {
+        for _ in 0..1000 {
+            app.update();
+            if app.world.query_filtered::<(), With<HintSearch>>().iter(&app.world).next().is_none() {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn show_hint_once_searched() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]));
+
+        press(&mut app, KeyCode::H);
+        assert_eq!(app.world.query_filtered::<(), With<HintSearch>>().iter(&app.world).count(), 1);
+        wait_for_hint(&mut app);
+
+        assert_eq!(app.world.query_filtered::<(), With<Hint>>().iter(&app.world).count(), 1);
+        assert_eq!(app.world.resource::<StageStats>().hints, 1);
+    }
+
+
+    #[test]
+    fn hint_after_undoing_coloured_key() {
+        let mut page = page(&[
+            "######",
+            "#PK..#",
+            "####.#",
+            "#..L.#",
+            "######",
+        ], &[]);
+        let key = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 1)).unwrap();
+        key.properties.insert(GimmickTag::Key, GimmickProperties { channel: Some(1), ..Default::default() });
+        let lock = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (3, 3)).unwrap();
+        lock.properties.insert(GimmickTag::Lock, GimmickProperties { channel: Some(1), require_keys: Some(1), ..Default::default() });
+        let mut app = new_stage_app(stage(vec![page]));
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+        let mut system_state = SystemState::<UndoRequester>::new(&mut app.world);
+        system_state.get_mut(&mut app.world).undo();
+        system_state.apply(&mut app.world);
+        for _ in 0..10 {
+            app.update();
+        }
+        press(&mut app, KeyCode::H);
+        wait_for_hint(&mut app);
+
+        let hint = *app.world.query_filtered::<&Transform, With<Hint>>().single(&app.world);
+        assert_eq!(hint.translation.truncate(), GridPos::new(2, 1).pixel());
+        assert_eq!(MoveDirection::from_transform(&hint), MoveDirection::Right);
+    }
+}
@@ -1,22 +1,31 @@
+use std::collections::VecDeque;
+
 use bevy::app::{App, Plugin, Update};
 use bevy::ecs::system::SystemParam;
-use bevy::prelude::{Commands, Component, Event, EventReader, EventWriter, in_state, IntoSystem, IntoSystemConfigs, NextState, Query, ResMut, Transform, With, Without};
-use bevy_trait_query::imports::Entity;
-use bevy_trait_query::One;
+use bevy::prelude::{Commands, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, Query, Res, ResMut, Resource, Transform, With, Without};
 use bevy_tweening::TweenCompleted;
 
+use crate::loader::json::GimmickProperties;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::move_linear;
-use crate::stage::playing::gimmick::player::Movable;
+use crate::simulation::board::Landing;
+use crate::simulation::grid::GridPos;
+use crate::simulation::motion::Motion;
+use crate::simulation::outcome::Outcome;
+use crate::stage::playing::gimmick::{Gimmick, move_linear};
+use crate::stage::playing::gimmick::player::Player;
+use crate::stage::playing::gimmick::tag::GimmickTag;
 use crate::stage::playing::move_direction::MoveDirection;
-use crate::stage::playing::move_position::MovePosition;
 use crate::stage::playing::phase::moving::goaled::{goaled_event_system, GoaledEvent};
+use crate::stage::playing::phase::moving::hole::{fall_event_system, FallEvent};
+use crate::stage::playing::phase::moving::ice_box::{IceBoxEvent, MovingIceBoxPlugin};
 use crate::stage::playing::phase::moving::key::{KeyEvent, MovingKeyPlugin};
 use crate::stage::playing::phase::moving::lock::{LockEvent, MovingLockPlugin};
 use crate::stage::playing::phase::moving::next_page::{next_page_event, NextPageEvent};
+use crate::stage::playing::phase::moving::portal::{MovingPortalPlugin, PortalEvent};
 use crate::stage::playing::phase::moving::stop_move::{stop_move_event_system, StopMoveEvent};
-use crate::stage::playing::phase::moving::turn::{turn_completed, turn_event_system, turn_pipe_system, TurnEvent};
+use crate::stage::playing::phase::moving::stuck::MovingStuckPlugin;
+use crate::stage::playing::phase::moving::teleport::{teleport_event_system, TeleportEvent};
+use crate::stage::playing::phase::moving::turn::{turn_event_system, TurnEvent};
 use crate::stage::state::StageState;
 
 
@@ -26,26 +35,64 @@ mod next_page;
 pub mod goaled;
 pub mod key;
 mod lock;
+mod ice_box;
+mod stuck;
+pub mod hole;
+mod teleport;
+mod portal;
+
+
+/// Sent as [`TweenCompleted::user_data`] once a motion has been played, animated or not.
+pub const MOTION_CODE: u64 = 1;
+
 
-#[derive(Event, Copy, Clone, Eq, PartialEq)]
-pub struct MoveEvent {
-    move_direction: MoveDirection,
-    col_entity: Entity,
+/// The motions of the current move as `Board::trace` worked them out, played back one at a time.
+#[derive(Resource, Debug, Clone)]
+pub struct MovePlan {
+    motions: VecDeque<Motion>,
+    outcome: Outcome,
+    last: Option<Motion>,
+    started: bool,
 }
 
-impl MoveEvent {
+
+impl MovePlan {
     #[inline]
-    pub const fn new(direction: MoveDirection, col_entity: Entity) -> Self {
+    pub fn new(motions: Vec<Motion>, outcome: Outcome) -> Self {
         Self {
-            move_direction: direction,
-            col_entity,
+            motions: motions.into(),
+            outcome,
+            last: None,
+            started: false,
         }
     }
 }
 
 
-#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Component)]
-pub struct CollisionTarget(MoveDirection);
+impl Default for MovePlan {
+    #[inline]
+    fn default() -> Self {
+        Self::new(Vec::new(), Outcome::Unmoved)
+    }
+}
+
+
+/// Lets [`next_motion_system`] go on after a motion that has nothing to animate.
+#[inline]
+pub(crate) fn motion_done(tween_writer: &mut EventWriter<TweenCompleted>) {
+    tween_writer.send(TweenCompleted {
+        entity: Entity::PLACEHOLDER,
+        user_data: MOTION_CODE,
+    });
+}
+
+
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct SlideEvent {
+    to: GridPos,
+    direction: MoveDirection,
+    landing: Landing,
+}
 
 
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
@@ -57,107 +104,143 @@ impl Plugin for PlayingMovingPlugin {
         app
             .add_plugins((
                 MovingKeyPlugin,
-                MovingLockPlugin
+                MovingLockPlugin,
+                MovingIceBoxPlugin,
+                MovingStuckPlugin,
+                MovingPortalPlugin
             ))
-            .add_event::<CollisionEvent>()
+            .init_resource::<MovePlan>()
+            .add_event::<SlideEvent>()
             .add_event::<TurnEvent>()
             .add_event::<NextPageEvent>()
             .add_event::<GoaledEvent>()
-            .add_systems(Update, move_event_system)
+            .add_event::<FallEvent>()
+            .add_event::<TeleportEvent>()
             .add_systems(Update, (
-                move_done_system,
-                collide_system,
+                next_motion_system,
+                slide_event_system,
                 stop_move_event_system,
-                turn_event_system.pipe(turn_pipe_system),
-                turn_completed,
+                turn_event_system,
                 next_page_event,
-                goaled_event_system
+                goaled_event_system,
+                fall_event_system,
+                teleport_event_system
             ).run_if(in_state(StageState::Moving)));
     }
 }
 
 
-fn move_event_system(
-    mut state: ResMut<NextState<StageState>>,
-    mut commands: Commands,
-    mut er: EventReader<MoveEvent>,
-    mut tween_writer: EventWriter<TweenCompleted>,
-    mut players: Query<(Entity, &mut Transform), With<Movable>>,
-    mut collides: Query<(Entity, &mut Transform, One<&dyn MovePosition>), (Without<Movable>, With<PageIndex>)>,
-) {
-    for MoveEvent { move_direction, col_entity } in er.iter().copied() {
-        let Some((ce, ct, move_position)) = collides.get_mut(col_entity).ok() else { continue; };
+#[derive(SystemParam)]
+struct MotionWriters<'w> {
+    slide: EventWriter<'w, SlideEvent>,
+    turn: EventWriter<'w, TurnEvent>,
+    key: EventWriter<'w, KeyEvent>,
+    lock: EventWriter<'w, LockEvent>,
+    ice_box: EventWriter<'w, IceBoxEvent>,
+    teleport: EventWriter<'w, TeleportEvent>,
+    portal: EventWriter<'w, PortalEvent>,
+    next_page: EventWriter<'w, NextPageEvent>,
+    stop_move: EventWriter<'w, StopMoveEvent>,
+    goaled: EventWriter<'w, GoaledEvent>,
+    fall: EventWriter<'w, FallEvent>,
+}
 
-        for (pe, mut pt) in players.iter_mut() {
-            let end = move_position.move_pos(ct.translation, move_direction);
 
-            move_linear(&mut commands.entity(pe), &mut tween_writer, &mut pt, end, move_direction);
-            commands.entity(ce).insert(CollisionTarget(move_direction));
-            state.set(StageState::Moving);
+/// Starts the next motion once the previous one is done, and ends the move by its outcome after the last.
+fn next_motion_system(
+    mut plan: ResMut<MovePlan>,
+    mut writers: MotionWriters,
+    mut er: EventReader<TweenCompleted>,
+) {
+    let done = er.iter().filter(|e| e.user_data == MOTION_CODE).count() != 0;
+    if plan.started && !done {
+        return;
+    }
+    plan.started = true;
+
+    let Some(motion) = plan.motions.pop_front() else {
+        match plan.outcome {
+            Outcome::Goaled => writers.goaled.send(GoaledEvent),
+            Outcome::Fell => writers.fall.send(FallEvent),
+            // A portal has already switched the page itself.
+            Outcome::NextPage if !matches!(plan.last, Some(Motion::Portal { .. })) => writers.next_page.send(NextPageEvent),
+            _ => writers.stop_move.send(StopMoveEvent),
         }
+        return;
+    };
+    plan.last = Some(motion);
+
+    match motion {
+        Motion::Slide { to, direction, landing } => writers.slide.send(SlideEvent { to, direction, landing }),
+        Motion::Turn(direction) => writers.turn.send(TurnEvent(direction)),
+        Motion::PickKey { pos, channel } => writers.key.send(KeyEvent { pos, channel }),
+        Motion::Unlock { pos, channel, keys } => writers.lock.send(LockEvent { pos, channel, keys }),
+        Motion::SlideIceBox { from, to } => writers.ice_box.send(IceBoxEvent { from, to }),
+        Motion::Teleport(to) => writers.teleport.send(TeleportEvent(to)),
+        Motion::Portal { page_index, to } => writers.portal.send(PortalEvent { page_index, to }),
+        Motion::NextPage => writers.next_page.send(NextPageEvent),
     }
 }
 
 
-#[derive(Event, Copy, Clone, Debug, Eq, PartialEq)]
-struct CollisionEvent(Entity, MoveDirection);
-
-
-fn move_done_system(
+fn slide_event_system(
     mut commands: Commands,
-    mut er: EventReader<TweenCompleted>,
-    mut ew: EventWriter<CollisionEvent>,
-    col: Query<(Entity, &CollisionTarget)>,
+    mut er: EventReader<SlideEvent>,
+    mut tween_writer: EventWriter<TweenCompleted>,
+    mut player: Query<(Entity, &mut Transform), With<Player>>,
+    cells: CellGimmicks,
 ) {
-    for _ in er.iter().filter(|e| e.user_data == 1) {
-        let Some((ce, CollisionTarget(move_direction))) = col.iter().next() else { continue; };
-        commands.entity(ce).remove::<CollisionTarget>();
-        ew.send(CollisionEvent(ce, *move_direction));
+    for SlideEvent { to, direction, landing } in er.iter().copied() {
+        let Ok((pe, mut pt)) = player.get_single_mut() else { continue; };
+        let z = match landing {
+            Landing::Into => cells.top_z(to),
+            Landing::InFront | Landing::OnTop => cells.top_z(to) + 1.,
+        };
+
+        move_linear(&mut commands.entity(pe), &mut tween_writer, &mut pt, to.pixel().extend(z), direction);
     }
 }
 
 
+/// The gimmicks on the fields, placed items included, looked up by the cells a [`Motion`] names.
 #[derive(SystemParam)]
-struct CollideWriters<'w> {
-    stop_move: EventWriter<'w, StopMoveEvent>,
-    turn: EventWriter<'w, TurnEvent>,
-    next_page: EventWriter<'w, NextPageEvent>,
-    goaled: EventWriter<'w, GoaledEvent>,
-    key: EventWriter<'w, KeyEvent>,
-    lock: EventWriter<'w, LockEvent>,
+pub struct CellGimmicks<'w, 's> {
+    page_index: Res<'w, PageIndex>,
+    gimmicks: Query<'w, 's, (Entity, &'static Transform, &'static GimmickTag, &'static PageIndex, Option<&'static GimmickProperties>), (With<Gimmick>, Without<Player>)>,
 }
 
 
-fn collide_system(
-    mut collide_writers: CollideWriters,
-    mut er: EventReader<CollisionEvent>,
-    cols: Query<&GimmickCollide>,
-) {
-    for CollisionEvent(ce, move_direction) in er.iter().copied() {
-        let Some(collide) = cols.get(ce).ok() else { continue; };
-
-        match collide {
-            GimmickCollide::StopMove => {
-                collide_writers.stop_move.send(StopMoveEvent);
-            }
-            GimmickCollide::Turn => {
-                collide_writers.turn.send(TurnEvent(ce));
-            }
-            GimmickCollide::NextPage => {
-                collide_writers.next_page.send(NextPageEvent);
-            }
-            GimmickCollide::Goal => {
-                collide_writers.goaled.send(GoaledEvent);
-            }
-            GimmickCollide::Key => {
-                collide_writers.key.send(KeyEvent(ce, move_direction));
-            }
-            GimmickCollide::Lock => {
-                collide_writers.lock.send(LockEvent(ce));
-            }
-            GimmickCollide::IceBox => {
-                todo!("IceBoxCollisionEvent")
-            }
-        }
+impl<'w, 's> CellGimmicks<'w, 's> {
+    #[inline]
+    pub fn page_index(&self) -> PageIndex {
+        *self.page_index
+    }
+
+
+    /// The topmost gimmick tagged `tag` on the cell at `pos` of the current page.
+    pub fn find(&self, pos: GridPos, tag: GimmickTag) -> Option<(Entity, Transform, GimmickProperties)> {
+        self
+            .gimmicks
+            .iter()
+            .filter(|(_, transform, gimmick_tag, page_index, _)| **gimmick_tag == tag && self.on_cell(pos, transform, page_index))
+            .max_by(|(_, prev, ..), (_, next, ..)| prev.translation.z.total_cmp(&next.translation.z))
+            .map(|(entity, transform, _, _, properties)| (entity, *transform, properties.copied().unwrap_or_default()))
+    }
+
+
+    /// The layer of the topmost gimmick on the cell at `pos` of the current page.
+    pub fn top_z(&self, pos: GridPos) -> f32 {
+        self
+            .gimmicks
+            .iter()
+            .filter(|(_, transform, _, page_index, _)| self.on_cell(pos, transform, page_index))
+            .map(|(_, transform, ..)| transform.translation.z)
+            .fold(0., f32::max)
+    }
+
+
+    #[inline]
+    fn on_cell(&self, pos: GridPos, transform: &Transform, page_index: &PageIndex) -> bool {
+        *page_index == *self.page_index && GridPos::from_pixel(transform.translation.truncate()) == pos
     }
 }
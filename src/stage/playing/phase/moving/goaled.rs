@@ -1,23 +1,91 @@
-use bevy::prelude::{Event, EventReader, NextState, ResMut};
+use bevy::prelude::{Commands, Event, EventReader, NextState, Res, ResMut, Time};
 
-use crate::gama_state::GameState;
+use crate::progress::{Progress, StageId};
+use crate::stage::replay::ReplayPlayback;
+use crate::stage::results::StageClear;
+use crate::stage::state::StageState;
+use crate::stage::stats::StageStats;
 
 #[derive(Event, Copy, Clone, Hash, Eq, PartialEq, Debug, Default)]
 pub struct GoaledEvent;
 
 
+/// A replay reaching the goal is not a clear of the player's own, so it leaves [`Progress`] alone,
+/// as does a stage played without a [`StageId`].
+#[allow(clippy::too_many_arguments)]
 pub fn goaled_event_system(
-    mut state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    mut state: ResMut<NextState<StageState>>,
+    mut progress: ResMut<Progress>,
     mut er: EventReader<GoaledEvent>,
+    playback: Option<Res<ReplayPlayback>>,
+    stage_id: Option<Res<StageId>>,
+    stats: Res<StageStats>,
+    time: Res<Time>,
 ) {
     if er.iter().next().is_some() {
-        state.set(GameState::StageSelect);
+        let result = stats.result(time.elapsed());
+        match stage_id.filter(|_| playback.is_none()) {
+            Some(stage_id) => {
+                commands.insert_resource(StageClear {
+                    result,
+                    previous_best: progress.record(&stage_id).copied(),
+                });
+                progress.add_clear(&stage_id, result);
+            }
+            None => {
+                commands.insert_resource(StageClear {
+                    result,
+                    previous_best: None,
+                });
+            }
+        }
+        state.set(StageState::Goaled);
     }
 }
 
 
 #[cfg(test)]
 mod tests {
+    use bevy::prelude::State;
+
+    use crate::progress::{Progress, StageId};
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::replay::{REPLAY_VERSION, ReplayAction, ReplayEvent, ReplayJson, ReplayPlayback, stage_hash};
+    use crate::stage::results::StageClear;
+    use crate::stage::state::StageState;
+    use crate::stage::tests::new_stage_app;
+
     #[test]
     fn page_move_spawned_items() {}
-}
\ No newline at end of file
+
+
+    #[test]
+    fn replay_does_not_record_progress() {
+        let stage = stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]);
+        let mut app = new_stage_app(stage.clone());
+        app.insert_resource(StageId::user(&stage.name));
+        app.insert_resource(ReplayPlayback::new(ReplayJson {
+            version: REPLAY_VERSION,
+            stage_name: stage.name.clone(),
+            stage_hash: stage_hash(&stage),
+            events: vec![ReplayEvent { time: 0., action: ReplayAction::Move { direction: MoveDirection::Right } }],
+        }));
+
+        for _ in 0..500 {
+            app.update();
+            if *app.world.resource::<State<StageState>>().get() == StageState::Goaled {
+                break;
+            }
+        }
+
+        assert_eq!(*app.world.resource::<State<StageState>>().get(), StageState::Goaled);
+        assert_eq!(*app.world.resource::<Progress>(), Progress::default());
+        assert_eq!(app.world.resource::<StageClear>().previous_best, None);
+    }
+}
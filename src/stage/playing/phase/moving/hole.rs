@@ -0,0 +1,22 @@
+use bevy::prelude::{Event, EventReader, NextState, ResMut};
+use bevy_undo2::prelude::UndoScheduler;
+
+use crate::stage::playing::phase::idle::UndoPlayerIdleEvent;
+use crate::stage::state::StageState;
+
+#[derive(Event, Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FallEvent;
+
+
+/// Closes the undo group of the move like a normal stop, so the fail phase can take the whole move back.
+pub fn fall_event_system(
+    mut state: ResMut<NextState<StageState>>,
+    mut er: EventReader<FallEvent>,
+    mut scheduler: UndoScheduler<UndoPlayerIdleEvent>,
+) {
+    if er.iter().next().is_some() {
+        scheduler.reserve(UndoPlayerIdleEvent);
+        scheduler.register_all_reserved();
+        state.set(StageState::Fail);
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::app::{App, Plugin, Update};
+use bevy::math::Vec3;
+use bevy::prelude::{Commands, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, Query, Transform};
+use bevy_tweening::{Animator, EaseMethod, Tween, TweenCompleted};
+use bevy_tweening::lens::TransformPositionLens;
+use bevy_undo2::prelude::{AppUndoEx, UndoScheduler};
+
+use crate::gama_state::GameState;
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::phase::moving::{CellGimmicks, MOTION_CODE, motion_done};
+use crate::stage::state::StageState;
+
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct IceBoxEvent {
+    pub from: GridPos,
+    pub to: GridPos,
+}
+
+
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct UndoIceBoxEvent(Entity, Vec3);
+
+
+#[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct MovingIceBoxPlugin;
+
+
+impl Plugin for MovingIceBoxPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<IceBoxEvent>()
+            .add_undo_event::<UndoIceBoxEvent>()
+            .add_systems(Update, ice_box_event_system
+                .run_if(in_state(StageState::Moving)),
+            )
+            .add_systems(Update, undo_ice_box_event_system
+                .run_if(in_state(GameState::Stage)),
+            );
+    }
+}
+
+
+fn ice_box_event_system(
+    mut commands: Commands,
+    mut scheduler: UndoScheduler<UndoIceBoxEvent>,
+    mut er: EventReader<IceBoxEvent>,
+    mut tween_writer: EventWriter<TweenCompleted>,
+    cells: CellGimmicks,
+) {
+    for IceBoxEvent { from, to } in er.iter().copied() {
+        let Some((ie, it, _)) = cells.find(from, GimmickTag::IceBox) else {
+            motion_done(&mut tween_writer);
+            continue;
+        };
+
+        let start = it.translation;
+        scheduler.reserve(UndoIceBoxEvent(ie, start));
+        commands
+            .entity(ie)
+            .insert(Animator::new(slide_tween(start, to.pixel().extend(start.z)).with_completed_event(MOTION_CODE)));
+    }
+}
+
+
+fn undo_ice_box_event_system(
+    mut er: EventReader<UndoIceBoxEvent>,
+    mut ice_boxes: Query<&mut Transform>,
+) {
+    for UndoIceBoxEvent(ie, pos) in er.iter().copied() {
+        let Ok(mut transform) = ice_boxes.get_mut(ie) else { continue; };
+        transform.translation = pos;
+    }
+}
+
+
+#[inline]
+fn slide_tween(start: Vec3, end: Vec3) -> Tween<Transform> {
+    Tween::new(
+        EaseMethod::Linear,
+        std::time::Duration::from_millis((end.distance(start) / 0.3) as u64),
+        TransformPositionLens {
+            start,
+            end,
+        },
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::math::Vec3Swizzles;
+    use bevy::prelude::Transform;
+
+    use crate::simulation::action::Action;
+    use crate::simulation::grid::GridPos;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::tests::{new_stage_app, play};
+
+    #[test]
+    fn ice_box_slides_where_board_says() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "#######",
+            "#PI...#",
+            "#######",
+        ], &[])]));
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+
+        let ice_box = app
+            .world
+            .query::<(&Transform, &GimmickTag)>()
+            .iter(&app.world)
+            .find(|(_, tag)| **tag == GimmickTag::IceBox)
+            .map(|(transform, _)| GridPos::from_pixel(transform.translation.xy()))
+            .unwrap();
+        assert_eq!(ice_box, GridPos::new(5, 1));
+    }
+}
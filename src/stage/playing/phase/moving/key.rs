@@ -1,66 +1,72 @@
-use std::ops::{AddAssign, Deref, SubAssign};
+use std::collections::BTreeMap;
 
 use bevy::app::{App, Plugin, Update};
-use bevy::math::Vec3;
-use bevy::prelude::{AssetServer, AudioBundle, Commands, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, PlaybackSettings, PreUpdate, Query, Res, ResMut, Resource, Transform, With, Without};
+use bevy::prelude::{AssetServer, AudioBundle, Commands, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, PlaybackSettings, Res, ResMut, Resource, Transform};
+use bevy_tweening::TweenCompleted;
 use bevy_undo2::prelude::{AppUndoEx, UndoScheduler};
 
 use crate::assets::gimmick::GimmickAssets;
 use crate::gama_state::GameState;
+use crate::loader::json::GimmickProperties;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::collide::GimmickCollide;
-use crate::stage::playing::gimmick::key::KeyBundle;
-use crate::stage::playing::gimmick::player::Player;
-use crate::stage::playing::move_direction::MoveDirection;
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::key::KeyColor;
+use crate::stage::playing::gimmick::tag::GimmickTag;
 use crate::stage::playing::phase::FieldParams;
-use crate::stage::playing::phase::start_move::StartMoveEvent;
+use crate::stage::playing::phase::moving::{CellGimmicks, motion_done};
 use crate::stage::state::StageState;
 
 #[derive(Event, Debug, Copy, Clone, PartialEq)]
-pub struct KeyEvent(pub Entity, pub MoveDirection);
+pub struct KeyEvent {
+    pub pos: GridPos,
+    pub channel: u8,
+}
 
 
 #[derive(Event, Debug, Copy, Clone, PartialEq)]
-pub struct UndoKeyEvent(Vec3, PageIndex);
+pub struct UndoKeyEvent(Transform, KeyColor, PageIndex, GimmickProperties);
 
 
-#[derive(Default, Resource, Debug, Copy, Clone)]
-pub struct KeyCounter(usize);
+/// The number of keys the player holds, per colour.
+#[derive(Default, Resource, Debug, Clone)]
+pub struct KeyCounter(BTreeMap<KeyColor, usize>);
 
 
 impl KeyCounter {
     #[inline(always)]
-    pub fn increment(&mut self) {
-        self.0 += 1;
+    pub fn count(&self, color: KeyColor) -> usize {
+        self.0.get(&color).copied().unwrap_or_default()
     }
 
 
     #[inline(always)]
-    pub fn decrement(&mut self) {
-        self.0 = self.0.checked_sub(1).unwrap_or_default();
+    pub fn increment(&mut self, color: KeyColor) {
+        self.add(color, 1);
     }
-}
 
 
-impl Deref for KeyCounter {
-    type Target = usize;
+    #[inline(always)]
+    pub fn decrement(&mut self, color: KeyColor) {
+        self.sub(color, 1);
+    }
+
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    #[inline(always)]
+    pub fn add(&mut self, color: KeyColor, count: usize) {
+        *self.0.entry(color).or_default() += count;
     }
-}
 
 
-impl AddAssign<usize> for KeyCounter {
-    fn add_assign(&mut self, rhs: usize) {
-        self.0 += rhs;
+    #[inline(always)]
+    pub fn sub(&mut self, color: KeyColor, count: usize) {
+        let keys = self.0.entry(color).or_default();
+        *keys = keys.saturating_sub(count);
     }
-}
 
 
-impl SubAssign<usize> for KeyCounter {
-    fn sub_assign(&mut self, rhs: usize) {
-        self.0 -= rhs;
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=(KeyColor, usize)> + '_ {
+        self.0.iter().map(|(color, count)| (*color, *count))
     }
 }
 
@@ -75,7 +81,7 @@ impl Plugin for MovingKeyPlugin {
             .add_event::<KeyEvent>()
             .add_undo_event::<UndoKeyEvent>()
             .init_resource::<KeyCounter>()
-            .add_systems(PreUpdate, (
+            .add_systems(Update, (
                 key_event_system
             ).run_if(in_state(StageState::Moving)))
             .add_systems(Update, (
@@ -89,22 +95,23 @@ fn key_event_system(
     mut commands: Commands,
     mut scheduler: UndoScheduler<UndoKeyEvent>,
     mut er: EventReader<KeyEvent>,
-    mut start_move_writer: EventWriter<StartMoveEvent>,
+    mut tween_writer: EventWriter<TweenCompleted>,
     mut key_counter: ResMut<KeyCounter>,
     asset_server: Res<AssetServer>,
-    keys: Query<(&Transform, &PageIndex), (With<GimmickCollide>, Without<Player>)>,
+    cells: CellGimmicks,
 ) {
-    for KeyEvent(ke, move_direction) in er.iter().copied() {
-        let Ok((kt, key_page_index)) = keys.get(ke) else { continue; };
-
-        commands.spawn(AudioBundle {
-            source: asset_server.load("audio/key.ogg"),
-            settings: PlaybackSettings::REMOVE,
-        });
-        key_counter.increment();
-        commands.entity(ke).despawn();
-        scheduler.reserve(UndoKeyEvent(kt.translation, *key_page_index));
-        start_move_writer.send(StartMoveEvent(move_direction));
+    for KeyEvent { pos, channel } in er.iter().copied() {
+        let key_color = KeyColor(channel);
+        if let Some((ke, kt, properties)) = cells.find(pos, GimmickTag::Key) {
+            commands.spawn(AudioBundle {
+                source: asset_server.load("audio/key.ogg"),
+                settings: PlaybackSettings::REMOVE,
+            });
+            commands.entity(ke).despawn();
+            scheduler.reserve(UndoKeyEvent(kt, key_color, cells.page_index(), properties));
+        }
+        key_counter.increment(key_color);
+        motion_done(&mut tween_writer);
     }
 }
 
@@ -116,38 +123,92 @@ fn undo_key_event_system(
     assets: Res<GimmickAssets>,
     field_params: FieldParams,
 ) {
-    for UndoKeyEvent(pos, page_index) in er.iter().copied() {
-        let gimmick = commands.spawn(KeyBundle::new(&assets, pos + Vec3::Z, page_index)).id();
-        field_params.add_child(&mut commands, gimmick);
-        key_counter.decrement();
+    for UndoKeyEvent(transform, key_color, page_index, properties) in er.iter().copied() {
+        let key = GimmickTag::Key.spawn(&mut commands, &assets, transform.translation, page_index, properties).id();
+        field_params.add_child_to(&mut commands, page_index, key);
+        key_counter.decrement(key_color);
     }
 }
 
 
 #[cfg(test)]
 mod tests {
-    use bevy::math::Vec3;
-    use bevy::prelude::{NextState, Transform};
-
-    use crate::assets::gimmick::GimmickAssets;
-    use crate::page::page_index::PageIndex;
-    use crate::stage::playing::gimmick::key::KeyBundle;
-    use crate::stage::playing::gimmick::player::PlayerBundle;
-    use crate::stage::playing::phase::moving::key::{KeyCounter, KeyEvent, MovingKeyPlugin};
-    use crate::stage::state::StageState;
-    use crate::stage::tests::new_playing_app;
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::system::SystemState;
+    use bevy_undo2::prelude::UndoRequester;
+
+    use crate::loader::json::GimmickProperties;
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::key::KeyColor;
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::playing::phase::moving::key::KeyCounter;
+    use crate::stage::tests::{new_stage_app, play};
 
     #[test]
     fn key_increment() {
-        let mut app = new_playing_app();
-        app.world.resource_mut::<NextState<StageState>>().set(StageState::Moving);
-        app.add_plugins(MovingKeyPlugin);
-        let key = app.world.spawn(KeyBundle::new(&GimmickAssets::default(), Vec3::ZERO, PageIndex::default())).id();
-        app.world.spawn(PlayerBundle::new(&GimmickAssets::default(), Vec3::ZERO, PageIndex::default()));
-        app.world.send_event(KeyEvent(key));
-        app.update();
-
-        assert_eq!(app.world.resource::<KeyCounter>().0, 1);
-        assert!(app.world.get::<Transform>(key).is_none());
+        let mut app = new_stage_app(stage(vec![page(&[
+            "######",
+            "#PK..#",
+            "######",
+        ], &[])]));
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+
+        assert_eq!(app.world.resource::<KeyCounter>().count(KeyColor::default()), 1);
+        assert!(app.world.query::<&GimmickTag>().iter(&app.world).all(|tag| *tag != GimmickTag::Key));
+    }
+
+
+    #[test]
+    fn count_keys_per_color() {
+        let mut page = page(&[
+            "######",
+            "#PK..#",
+            "######",
+        ], &[]);
+        let key = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 1)).unwrap();
+        key.properties.insert(GimmickTag::Key, GimmickProperties { channel: Some(1), ..Default::default() });
+        let mut app = new_stage_app(stage(vec![page]));
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+
+        let counter = app.world.resource::<KeyCounter>();
+        assert_eq!(counter.count(KeyColor(1)), 1);
+        assert_eq!(counter.count(KeyColor::default()), 0);
     }
-}
\ No newline at end of file
+
+
+    #[test]
+    fn undo_key_keeps_properties() {
+        let mut page = page(&[
+            "######",
+            "#PK..#",
+            "######",
+        ], &[]);
+        let properties = GimmickProperties { channel: Some(3), ..Default::default() };
+        let key = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 1)).unwrap();
+        key.properties.insert(GimmickTag::Key, properties);
+        let mut app = new_stage_app(stage(vec![page]));
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+        assert_eq!(app.world.resource::<KeyCounter>().count(KeyColor(3)), 1);
+
+        let mut system_state = SystemState::<UndoRequester>::new(&mut app.world);
+        system_state.get_mut(&mut app.world).undo();
+        system_state.apply(&mut app.world);
+        for _ in 0..10 {
+            app.update();
+        }
+        let keys = app
+            .world
+            .query::<(&GimmickTag, &GimmickProperties, &KeyColor)>()
+            .iter(&app.world)
+            .filter(|(tag, ..)| **tag == GimmickTag::Key)
+            .map(|(_, properties, key_color)| (*properties, *key_color))
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![(properties, KeyColor(3))]);
+        assert_eq!(app.world.resource::<KeyCounter>().count(KeyColor(3)), 0);
+    }
+}
@@ -1,29 +1,31 @@
 use bevy::app::{App, Plugin, Update};
-use bevy::audio::{AudioBundle};
-use bevy::prelude::{AssetServer, Commands, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, PlaybackSettings, Query, Res, ResMut, Transform, With};
-
-use bevy_trait_query::imports::Component;
+use bevy::audio::AudioBundle;
+use bevy::prelude::{AssetServer, Commands, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, PlaybackSettings, Res, ResMut, Transform};
+use bevy_tweening::TweenCompleted;
 use bevy_undo2::prelude::{AppUndoEx, UndoScheduler};
 
-
 use crate::assets::gimmick::GimmickAssets;
 use crate::gama_state::GameState;
+use crate::loader::json::GimmickProperties;
 use crate::page::page_index::PageIndex;
-use crate::stage::playing::gimmick::lock::{LockBundle, RequireKeys};
-use crate::stage::playing::gimmick::player::Player;
-use crate::stage::playing::move_direction::MoveDirection;
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::key::KeyColor;
+use crate::stage::playing::gimmick::lock::RequireKeys;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::phase::FieldParams;
+use crate::stage::playing::phase::moving::{CellGimmicks, motion_done};
 use crate::stage::playing::phase::moving::key::KeyCounter;
-use crate::stage::playing::phase::start_move::{StartMoveDownEvent, StartMoveEvent};
 use crate::stage::state::StageState;
 
 #[derive(Event, Copy, Clone, Debug)]
-pub struct LockEvent(pub Entity);
-
-#[derive(Event, Copy, Clone, Debug)]
-pub struct UnLockEvent(Entity, Transform, RequireKeys, PageIndex);
+pub struct LockEvent {
+    pub pos: GridPos,
+    pub channel: u8,
+    pub keys: usize,
+}
 
 #[derive(Event, Copy, Clone, Debug)]
-pub struct UndoUnLockEvent(Transform, RequireKeys, PageIndex);
+pub struct UndoUnLockEvent(Transform, RequireKeys, KeyColor, PageIndex, GimmickProperties);
 
 
 pub struct MovingLockPlugin;
@@ -32,12 +34,10 @@ impl Plugin for MovingLockPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<LockEvent>()
-            .add_event::<UnLockEvent>()
             .add_undo_event::<UndoUnLockEvent>()
-            .add_systems(Update, (
-                lock_event_system,
-                unlock_event_system,
-            ).run_if(in_state(StageState::Moving)))
+            .add_systems(Update, lock_event_system
+                .run_if(in_state(StageState::Moving)),
+            )
             .add_systems(Update, undo_unlock_event_system
                 .run_if(in_state(GameState::Stage)),
             );
@@ -46,46 +46,26 @@ impl Plugin for MovingLockPlugin {
 
 
 fn lock_event_system(
-    mut er: EventReader<LockEvent>,
-    mut start_move_writer: EventWriter<StartMoveEvent>,
-    mut unlock_writer: EventWriter<UnLockEvent>,
-    key_counter: Res<KeyCounter>,
-    player: Query<&Transform, With<Player>>,
-    locks: Query<(&Transform, &RequireKeys, &PageIndex)>,
-) {
-    for LockEvent(le) in er.iter().copied() {
-        let Ok((lt, require_keys, page_index)) = locks.get(le) else { continue; };
-
-        if require_keys.0 <= **key_counter {
-            unlock_writer.send(UnLockEvent(le, *lt, *require_keys, *page_index));
-        } else {
-            start_move_writer.send(StartMoveEvent(MoveDirection::from_transform(player.single())));
-        }
-    }
-}
-
-
-#[derive(Component)]
-struct UnlockAudioTarget;
-
-
-fn unlock_event_system(
     mut commands: Commands,
     mut scheduler: UndoScheduler<UndoUnLockEvent>,
-    mut unlock_reader: EventReader<UnLockEvent>,
-    mut start_move_down_writer: EventWriter<StartMoveDownEvent>,
+    mut er: EventReader<LockEvent>,
+    mut tween_writer: EventWriter<TweenCompleted>,
     mut key_counter: ResMut<KeyCounter>,
     asset_server: Res<AssetServer>,
+    cells: CellGimmicks,
 ) {
-    for UnLockEvent(e, transform, require_keys, page_index) in unlock_reader.iter().copied() {
-        commands.spawn(AudioBundle {
-            source: asset_server.load("audio/unlock.ogg"),
-            settings: PlaybackSettings::REMOVE,
-        });
-        *key_counter -= require_keys.0;
-        start_move_down_writer.send(StartMoveDownEvent(transform.translation.z));
-        commands.entity(e).despawn();
-        scheduler.reserve(UndoUnLockEvent(transform, require_keys, page_index));
+    for LockEvent { pos, channel, keys } in er.iter().copied() {
+        let key_color = KeyColor(channel);
+        if let Some((le, lt, properties)) = cells.find(pos, GimmickTag::Lock) {
+            commands.spawn(AudioBundle {
+                source: asset_server.load("audio/unlock.ogg"),
+                settings: PlaybackSettings::REMOVE,
+            });
+            commands.entity(le).despawn();
+            scheduler.reserve(UndoUnLockEvent(lt, RequireKeys(keys), key_color, cells.page_index(), properties));
+        }
+        key_counter.sub(key_color, keys);
+        motion_done(&mut tween_writer);
     }
 }
 
@@ -95,10 +75,61 @@ fn undo_unlock_event_system(
     mut er: EventReader<UndoUnLockEvent>,
     mut key_counter: ResMut<KeyCounter>,
     assets: Res<GimmickAssets>,
+    field_params: FieldParams,
 ) {
-    for UndoUnLockEvent(transform, require_keys, page_index) in er.iter().copied() {
-        *key_counter += require_keys.0;
+    for UndoUnLockEvent(transform, require_keys, key_color, page_index, properties) in er.iter().copied() {
+        key_counter.add(key_color, require_keys.0);
+        let lock = GimmickTag::Lock.spawn(&mut commands, &assets, transform.translation, page_index, properties).id();
+        field_params.add_child_to(&mut commands, page_index, lock);
+    }
+}
 
-        commands.spawn(LockBundle::new(&assets, transform.translation, page_index));
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::system::SystemState;
+    use bevy_undo2::prelude::UndoRequester;
+
+    use crate::loader::json::GimmickProperties;
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::key::KeyColor;
+    use crate::stage::playing::gimmick::lock::RequireKeys;
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::tests::{new_stage_app, play};
+
+    #[test]
+    fn undo_unlock_keeps_properties() {
+        let mut page = page(&[
+            "######",
+            "#PK..#",
+            "######",
+        ], &[]);
+        let key = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (2, 1)).unwrap();
+        key.properties.insert(GimmickTag::Key, GimmickProperties { channel: Some(2), ..Default::default() });
+        let properties = GimmickProperties { require_keys: Some(1), channel: Some(2), ..Default::default() };
+        let lock = page.cells.iter_mut().find(|cell| (cell.col, cell.row) == (3, 1)).unwrap();
+        lock.tags.push(GimmickTag::Lock);
+        lock.properties.insert(GimmickTag::Lock, properties);
+        let mut app = new_stage_app(stage(vec![page]));
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+        assert_eq!(app.world.query::<&RequireKeys>().iter(&app.world).count(), 0);
+
+        let mut system_state = SystemState::<UndoRequester>::new(&mut app.world);
+        system_state.get_mut(&mut app.world).undo();
+        system_state.apply(&mut app.world);
+        for _ in 0..10 {
+            app.update();
+        }
+        let locks = app
+            .world
+            .query::<(&GimmickProperties, &RequireKeys, &KeyColor)>()
+            .iter(&app.world)
+            .map(|(properties, require_keys, key_color)| (*properties, *require_keys, *key_color))
+            .collect::<Vec<_>>();
+        assert_eq!(locks, vec![(properties, RequireKeys(1), KeyColor(2))]);
     }
 }
\ No newline at end of file
@@ -0,0 +1,139 @@
+use bevy::app::{App, Plugin, Update};
+use bevy::log::debug;
+use bevy::prelude::{Commands, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, Query, Transform, With, Without};
+use bevy_tweening::TweenCompleted;
+use bevy_undo2::prelude::{AppUndoEx, UndoScheduler};
+
+use crate::gama_state::GameState;
+use crate::page::page_index::PageIndex;
+use crate::page::page_param::PageParams;
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::GimmickItemSpawned;
+use crate::stage::playing::gimmick::player::Player;
+use crate::stage::playing::phase::moving::motion_done;
+use crate::stage::playing::phase::next_page::update_items_page_index;
+use crate::stage::state::StageState;
+use crate::stage_edit::page::Field;
+
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct PortalEvent {
+    pub page_index: usize,
+    pub to: GridPos,
+}
+
+
+/// Holds the page the player came from; the player's own position is restored by `UndoPlayerEvent`.
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+struct UndoPortalEvent(PageIndex);
+
+
+#[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct MovingPortalPlugin;
+
+
+impl Plugin for MovingPortalPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<PortalEvent>()
+            .add_undo_event::<UndoPortalEvent>()
+            .add_systems(Update, (
+                portal_event_system
+            ).run_if(in_state(StageState::Moving)))
+            .add_systems(Update, (
+                undo_portal_event_system
+            ).run_if(in_state(GameState::Stage)));
+    }
+}
+
+
+/// Sends the player to the portal's target cell and carries the spawned items over like [`StageState::NextPage`] does.
+#[allow(clippy::too_many_arguments)]
+fn portal_event_system(
+    mut er: EventReader<PortalEvent>,
+    mut scheduler: UndoScheduler<UndoPortalEvent>,
+    mut tween_writer: EventWriter<TweenCompleted>,
+    mut page_params: PageParams,
+    mut player: Query<&mut Transform, With<Player>>,
+    commands: Commands,
+    fields: Query<(Entity, &PageIndex), With<Field>>,
+    items: Query<(Entity, &mut PageIndex), (With<GimmickItemSpawned>, With<PageIndex>, Without<Field>)>,
+) {
+    let Some(PortalEvent { page_index, to }) = er.iter().next().copied() else { return; };
+    let Ok(mut pt) = player.get_single_mut() else { return; };
+
+    debug!("portal to page {page_index}");
+    let previous_page = PageIndex::new(page_params.page_index());
+    let page_index = page_params.move_to(PageIndex::new(page_index));
+    pt.translation = to.pixel().extend(pt.translation.z);
+    scheduler.reserve(UndoPortalEvent(previous_page));
+    update_items_page_index(commands, items, page_index, fields);
+    motion_done(&mut tween_writer);
+}
+
+
+fn undo_portal_event_system(
+    mut er: EventReader<UndoPortalEvent>,
+    mut page_params: PageParams,
+    commands: Commands,
+    fields: Query<(Entity, &PageIndex), With<Field>>,
+    items: Query<(Entity, &mut PageIndex), (With<GimmickItemSpawned>, With<PageIndex>, Without<Field>)>,
+) {
+    if let Some(UndoPortalEvent(previous_page)) = er.iter().next().copied() {
+        debug!("undo: portal");
+        let page_index = page_params.move_to(previous_page);
+        update_items_page_index(commands, items, page_index, fields);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::system::SystemState;
+    use bevy::math::Vec3Swizzles;
+    use bevy::prelude::{Transform, With};
+    use bevy_undo2::prelude::UndoRequester;
+
+    use crate::loader::json::{GimmickProperties, PortalTarget, StageCell};
+    use crate::page::page_index::PageIndex;
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::player::Player;
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::tests::{new_stage_app, play};
+
+    #[test]
+    fn portal_to_other_page_and_undo() {
+        let mut first = page(&[
+            "#####",
+            "#P..#",
+            "#####",
+        ], &[]);
+        let portal = first.cells.iter_mut().find(|cell| (cell.col, cell.row) == (3, 1)).unwrap();
+        portal.tags.push(GimmickTag::Portal);
+        portal.properties.insert(GimmickTag::Portal, GimmickProperties { target: Some(PortalTarget { page: 1, col: 2, row: 1 }), ..Default::default() });
+        let mut app = new_stage_app(stage(vec![first, page(&[
+            "#####",
+            "#...#",
+            "#####",
+        ], &[])]));
+        let start = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+        let player = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+        assert_eq!(*app.world.resource::<PageIndex>(), PageIndex::new(1));
+        assert!(player.translation.xy().abs_diff_eq(StageCell::new(2, 1, Vec::new()).pixel(), 0.1));
+        assert_eq!(*app.world.query_filtered::<&PageIndex, With<Player>>().single(&app.world), PageIndex::new(1));
+
+        let mut system_state = SystemState::<UndoRequester>::new(&mut app.world);
+        system_state.get_mut(&mut app.world).undo();
+        system_state.apply(&mut app.world);
+        for _ in 0..10 {
+            app.update();
+        }
+        let player = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+        assert_eq!(*app.world.resource::<PageIndex>(), PageIndex::new(0));
+        assert_eq!(player.translation, start.translation);
+    }
+}
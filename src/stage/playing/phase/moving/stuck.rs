@@ -0,0 +1,172 @@
+use bevy::app::{App, Plugin, Update};
+use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy::prelude::{AlignItems, Color, Commands, Component, Condition, default, Entity, EventReader, in_state, IntoSystemConfigs, JustifyContent, NodeBundle, Or, PositionType, Query, Res, resource_exists_and_changed, TextBundle, TextStyle, UiRect, Val, With};
+use bevy::ui::{BackgroundColor, Style, ZIndex};
+
+use crate::assets::font::FontAssets;
+use crate::gama_state::GameState;
+use crate::simulation::solver::Solve;
+use crate::stage::history::ActionHistory;
+use crate::stage::playing::phase::moving::stop_move::StopMoveEvent;
+use crate::stage::playing::runtime_board::RuntimeBoard;
+use crate::stage::playing::solve_task::SolveTask;
+
+/// Shown when the goal can no longer be reached without undoing.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct StuckBanner;
+
+
+/// The search started by the last stop; a search that gives up counts as reachable.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+struct StuckCheck;
+
+
+#[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct MovingStuckPlugin;
+
+
+impl Plugin for MovingStuckPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // Not limited to `StageState::Moving`: the stop is often read on the frame the stage is already idle.
+            .add_systems(Update, (
+                stuck_check_system,
+                stuck_banner_system
+            ).run_if(in_state(GameState::Stage)))
+            .add_systems(Update, clear_stuck_banner_system
+                .run_if(in_state(GameState::Stage).and_then(resource_exists_and_changed::<ActionHistory>())),
+            );
+    }
+}
+
+
+fn stuck_check_system(
+    mut commands: Commands,
+    mut er: EventReader<StopMoveEvent>,
+    runtime_board: RuntimeBoard,
+    checks: Query<Entity, With<StuckCheck>>,
+    banners: Query<Entity, With<StuckBanner>>,
+) {
+    if er.iter().count() == 0 || !banners.is_empty() {
+        return;
+    }
+    let Ok((board, state)) = runtime_board.board() else { return; };
+
+    for check in checks.iter() {
+        commands.entity(check).despawn();
+    }
+    commands.spawn((StuckCheck, SolveTask::new(board, state)));
+}
+
+
+fn stuck_banner_system(
+    mut commands: Commands,
+    mut checks: Query<(Entity, &mut SolveTask), With<StuckCheck>>,
+    fonts: Res<FontAssets>,
+    banners: Query<Entity, With<StuckBanner>>,
+) {
+    for (check, mut task) in checks.iter_mut() {
+        let Some(solve) = task.poll() else { continue; };
+        commands.entity(check).despawn();
+        if matches!(solve, Solve::Unsolvable) && banners.is_empty() {
+            spawn_stuck_banner(&mut commands, &fonts);
+        }
+    }
+}
+
+
+fn spawn_stuck_banner(commands: &mut Commands, fonts: &FontAssets) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            top: Val::Px(16.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        z_index: ZIndex::Global(5),
+        ..default()
+    })
+        .insert(StuckBanner)
+        .with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(16.), Val::Px(6.)),
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor::from(Color::rgba(0.1, 0.1, 0.2, 0.7)),
+                ..default()
+            })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Stuck \u{2014} undo? (R)", TextStyle {
+                        font: fonts.button_text.clone(),
+                        font_size: 24.,
+                        color: Color::WHITE,
+                    }));
+                });
+        });
+}
+
+
+/// Also drops a search still running, since it was started from a position that has been undone.
+fn clear_stuck_banner_system(
+    mut commands: Commands,
+    entities: Query<Entity, Or<(With<StuckBanner>, With<StuckCheck>)>>,
+) {
+    for entity in entities.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::app::App;
+    use bevy::prelude::With;
+
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::phase::moving::stop_move::StopMoveEvent;
+    use crate::stage::playing::phase::moving::stuck::{StuckBanner, StuckCheck};
+    use crate::stage::tests::new_stage_app;
+
+    #[test]
+    fn banner_when_goal_unreachable() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "######",
+            "#P.#G#",
+            "######",
+        ], &[])]));
+        app.world.send_event(StopMoveEvent);
+        wait_for_check(&mut app);
+
+        assert_eq!(app.world.query_filtered::<(), With<StuckBanner>>().iter(&app.world).count(), 1);
+    }
+
+
+    #[test]
+    fn no_banner_while_solvable() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]));
+        app.world.send_event(StopMoveEvent);
+        wait_for_check(&mut app);
+
+        assert_eq!(app.world.query_filtered::<(), With<StuckBanner>>().iter(&app.world).count(), 0);
+    }
+
+
+    /// Steps until the search started by the stop has finished and its banner, if any, is spawned.
+    fn wait_for_check(app: &mut App) {
+        app.update();
+        for _ in 0..1000 {
+            app.update();
+            if app.world.query_filtered::<(), With<StuckCheck>>().iter(&app.world).next().is_none() {
+                return;
+            }
+        }
+        panic!("the stuck check did not finish");
+    }
+}
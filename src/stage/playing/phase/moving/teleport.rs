@@ -0,0 +1,68 @@
+use bevy::prelude::{Event, EventReader, EventWriter, Query, Transform, With};
+use bevy_tweening::TweenCompleted;
+
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::player::Player;
+use crate::stage::playing::phase::moving::{CellGimmicks, motion_done};
+
+/// Puts the player on the partner teleporter at this cell; the slide goes on from there.
+#[derive(Event, Debug, Copy, Clone, PartialEq)]
+pub struct TeleportEvent(pub GridPos);
+
+
+/// The `UndoPlayerEvent` reserved when the move started takes the player back from the partner on undo.
+pub fn teleport_event_system(
+    mut er: EventReader<TeleportEvent>,
+    mut tween_writer: EventWriter<TweenCompleted>,
+    mut player: Query<&mut Transform, With<Player>>,
+    cells: CellGimmicks,
+) {
+    for TeleportEvent(to) in er.iter().copied() {
+        if let Ok(mut pt) = player.get_single_mut() {
+            pt.translation = to.pixel().extend(cells.top_z(to) + 1.);
+        }
+        motion_done(&mut tween_writer);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::ecs::system::SystemState;
+    use bevy::math::Vec3Swizzles;
+    use bevy::prelude::{Transform, With};
+    use bevy_undo2::prelude::UndoRequester;
+
+    use crate::loader::json::StageCell;
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::gimmick::player::Player;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::tests::{new_stage_app, play};
+
+    #[test]
+    fn teleport_and_undo() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "######",
+            "#PX..#",
+            "######",
+            "#.X..#",
+            "######",
+        ], &[])]));
+        let start = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+        let player = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+        assert!(player.translation.xy().abs_diff_eq(StageCell::new(4, 3, Vec::new()).pixel(), 0.1));
+
+        let mut system_state = SystemState::<UndoRequester>::new(&mut app.world);
+        system_state.get_mut(&mut app.world).undo();
+        system_state.apply(&mut app.world);
+        for _ in 0..10 {
+            app.update();
+        }
+        let player = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+        assert_eq!(player.translation, start.translation);
+    }
+}
@@ -1,51 +1,29 @@
 use bevy::math::Quat;
-use bevy::prelude::{Commands, Entity, Event, EventReader, EventWriter, In, Query, Transform, With};
-use bevy_tweening::{Animator, EaseMethod, Tween, TweenCompleted};
+use bevy::prelude::{Commands, Entity, Event, EventReader, Query, Transform, With};
+use bevy_tweening::{Animator, EaseMethod, Tween};
 use bevy_tweening::lens::TransformRotationLens;
 
 use crate::stage::playing::gimmick::player::Player;
 use crate::stage::playing::move_direction::MoveDirection;
-use crate::stage::playing::phase::start_move::{StartMoveEvent, UndoPlayerEvent};
+use crate::stage::playing::phase::moving::MOTION_CODE;
 
+/// The player faces a new direction, from a turn tile or an arrow.
 #[derive(Event, Debug, Copy, Clone, PartialEq)]
-pub struct TurnEvent(pub Entity);
-
-
-const TURN_CODE: u64 = 52123;
+pub struct TurnEvent(pub MoveDirection);
 
 
 pub fn turn_event_system(
     mut commands: Commands,
     mut er: EventReader<TurnEvent>,
     player: Query<(Entity, &Transform), With<Player>>,
-    turn: Query<&Transform>,
-) -> Option<MoveDirection> {
-    let TurnEvent(ce) = er.iter().next().copied()?;
-
-    let (pe, pt) = player.single();
-    commands.entity(pe).insert(UndoPlayerEvent::new(*pt));
-    let ct = turn.get(ce).ok()?;
-    let pd = MoveDirection::from_angle(pt.rotation.to_axis_angle().1);
-    let td = MoveDirection::from_angle(ct.rotation.to_axis_angle().1);
-
-    Some(td.turn(pd))
-}
-
-
-pub fn turn_pipe_system(
-    In(next_dir): In<Option<MoveDirection>>,
-    mut commands: Commands,
-    player: Query<(Entity, &Transform), With<Player>>,
 ) {
-    let Some(next_dir) = next_dir else { return; };
-    let (pe, transform, ) = player.single();
-
-    let start = transform.rotation;
-    let end = next_dir.quat();
+    for TurnEvent(direction) in er.iter().copied() {
+        let Ok((pe, pt)) = player.get_single() else { continue; };
 
-    commands
-        .entity(pe)
-        .insert(Animator::new(turn_tween(start, end).with_completed_event(TURN_CODE)));
+        commands
+            .entity(pe)
+            .insert(Animator::new(turn_tween(pt.rotation, direction.quat()).with_completed_event(MOTION_CODE)));
+    }
 }
 
 
@@ -62,66 +40,50 @@ pub fn turn_tween(start: Quat, end: Quat) -> Tween<Transform> {
 }
 
 
-#[inline]
-pub fn turn_completed(
-    mut commands: Commands,
-    mut er: EventReader<TweenCompleted>,
-    mut start_move_writer: EventWriter<StartMoveEvent>,
-    mut undo_player_writer: EventWriter<UndoPlayerEvent>,
-    player: Query<(Entity, &Transform, &UndoPlayerEvent), With<Player>>,
-) {
-    for _ in er.iter().filter(|e| e.user_data == TURN_CODE) {
-        let (pe, pt, undo_player_event) = player.single();
-
-        start_move_writer.send(StartMoveEvent(MoveDirection::from_transform(pt)));
-        commands.entity(pe).remove::<UndoPlayerEvent>();
-        undo_player_writer.send(*undo_player_event);
-    }
-}
-
-
 #[cfg(test)]
 mod test {
-    use std::f32::consts::PI;
-
-    use bevy::app::{App, Update};
-    use bevy::math::Quat;
-    use bevy::prelude::{In, IntoSystem, Transform};
-    use bevy::sprite::SpriteBundle;
-    use bevy::utils::default;
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::prelude::{Transform, With};
 
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
     use crate::stage::playing::gimmick::player::Player;
     use crate::stage::playing::move_direction::MoveDirection;
-    use crate::stage::playing::phase::moving::turn::{turn_event_system, TurnEvent};
+    use crate::stage::tests::{new_stage_app, play};
 
     macro_rules! turn_test {
-        ($name: ident, $player: expr, $col: expr, $expect: expr) => {
+        ($name: ident, $player: expr, $turn: expr, $expect: expr) => {
             #[test]
             fn $name() {
-                let mut app = App::new();
-                app.add_event::<TurnEvent>();
-                app.add_systems(Update, turn_event_system.pipe(|In(dir): In<Option<MoveDirection>>| {
-                    assert_eq!(dir, Some($expect));
-                }));
-
-                app.world.spawn(SpriteBundle { transform: Transform::from_rotation(Quat::from_rotation_z($player * PI)), ..default() }).insert(Player);
-                let id = app.world.spawn(SpriteBundle { transform: Transform::from_rotation(Quat::from_rotation_z($col * PI)), ..default() }).id();
-
-                app.world.send_event(TurnEvent(id));
-
-                app.update();
+                assert_eq!($turn.turn($player), $expect);
             }
         }
     }
 
-    turn_test!(up, 1.5, 0., MoveDirection::Up);
-    turn_test!(left, 1.0, 0., MoveDirection::Left);
-    turn_test!(right, 0., 0., MoveDirection::Right);
-    turn_test!(down, 0.5, 0., MoveDirection::Down);
+    turn_test!(up, MoveDirection::Right, MoveDirection::Up, MoveDirection::Up);
+    turn_test!(left, MoveDirection::Down, MoveDirection::Up, MoveDirection::Left);
+    turn_test!(right, MoveDirection::Up, MoveDirection::Up, MoveDirection::Right);
+    turn_test!(down, MoveDirection::Left, MoveDirection::Up, MoveDirection::Down);
+
+
+    turn_test!(up2, MoveDirection::Right, MoveDirection::Left, MoveDirection::Down);
+    turn_test!(left2, MoveDirection::Up, MoveDirection::Left, MoveDirection::Left);
+    turn_test!(right2, MoveDirection::Left, MoveDirection::Left, MoveDirection::Up);
+    turn_test!(down2, MoveDirection::Down, MoveDirection::Left, MoveDirection::Right);
+
 
+    #[test]
+    fn player_faces_where_turn_sends_it() {
+        let mut app = new_stage_app(stage(vec![page(&[
+            "#####",
+            "#...#",
+            "#P.T#",
+            "#####",
+        ], &[])]));
 
-    turn_test!(up2, 1.5, 0.5, MoveDirection::Down);
-    turn_test!(left2, 0., 0.5, MoveDirection::Left);
-    turn_test!(right2, 0.5, 0.5, MoveDirection::Up);
-    turn_test!(down2, 1., 0.5, MoveDirection::Right);
-}
\ No newline at end of file
+        play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right));
+
+        let player = *app.world.query_filtered::<&Transform, With<Player>>().single(&app.world);
+        assert_eq!(MoveDirection::from_transform(&player), MoveDirection::Up);
+    }
+}
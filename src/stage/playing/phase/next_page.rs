@@ -64,7 +64,7 @@ fn undo_next_page_event_system(
 }
 
 
-fn update_items_page_index(
+pub(crate) fn update_items_page_index(
     mut commands: Commands,
     mut items: Query<(Entity, &mut PageIndex), (With<GimmickItemSpawned>, With<PageIndex>, Without<Field>)>,
     next_page_index: PageIndex,
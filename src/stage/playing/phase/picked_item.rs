@@ -10,8 +10,11 @@ use bevy_undo2::prelude::{AppUndoEx, UndoScheduler};
 use crate::assets::gimmick::GimmickAssets;
 use crate::button::SpriteInteraction;
 use crate::gama_state::GameState;
+use crate::loader::json::{GimmickProperties, ItemCell, StageCell};
 use crate::GameCursorParams;
 use crate::page::page_index::PageIndex;
+use crate::stage::history::{ActionHistory, PlayerAction};
+use crate::stage::replay::{ReplayAction, ReplayRecord};
 use crate::stage::playing::gimmick::{Floor, GimmickItem, GimmickItemDisabled, GimmickItemSpawned};
 use crate::stage::playing::gimmick::tag::GimmickTag;
 use crate::stage::state::StageState;
@@ -26,7 +29,7 @@ pub struct PickedItemEvent(pub Entity);
 
 
 #[derive(Copy, Clone, PartialEq, Debug, Event)]
-struct SpawnGimmickEvent(Vec3, Entity, GimmickTag);
+pub struct SpawnGimmickEvent(pub Vec3, pub Entity, pub GimmickTag);
 
 
 #[derive(Copy, Clone, PartialEq, Debug, Event)]
@@ -52,11 +55,11 @@ impl Plugin for PlayingPickedItemPlugin {
             .add_systems(OnExit(StageState::PickedItem), stage_un_focus_system)
             .add_systems(Update, (
                 pick_event_item_system,
+                spawn_item_system,
                 undo_spawn_item_event_system
             ).run_if(in_state(GameState::Stage)))
             .add_systems(Update, (
                 click_floor_system,
-                spawn_item_system,
                 cancel_item_system
             ).run_if(in_state(StageState::PickedItem)));
     }
@@ -146,18 +149,31 @@ fn spawn_item_system(
     mut er: EventReader<SpawnGimmickEvent>,
     mut cursor: GameCursorParams,
     mut scheduler: UndoScheduler<UndoSpawnGimmickEvent>,
+    mut history: ResMut<ActionHistory>,
+    mut record: ReplayRecord,
     assets: Res<GimmickAssets>,
     page_index: Res<PageIndex>,
     fields: Query<(Entity, &PageIndex), With<Field>>,
+    items: Query<(&Transform, Option<&GimmickProperties>), With<GimmickItem>>,
 ) {
     for SpawnGimmickEvent(spawn_pos, item_entity, tag) in er.iter().copied() {
+        let Ok((item_transform, properties)) = items.get(item_entity) else { continue; };
+        let properties = properties.copied().unwrap_or_default();
+        let cell = StageCell::from_pixel(spawn_pos.truncate(), Vec::new());
+        record.record(ReplayAction::Place {
+            page: page_index.0,
+            item_row: ItemCell::row_from_pixel(item_transform.translation.y),
+            col: cell.col,
+            row: cell.row,
+        });
+
         commands
             .entity(item_entity)
             .remove::<GimmickItem>()
             .insert(GimmickItemDisabled(tag));
 
         let gimmick_entity = tag
-            .spawn(&mut commands, &assets, spawn_pos + Vec3::Z, *page_index)
+            .spawn(&mut commands, &assets, spawn_pos + Vec3::Z, *page_index, properties)
             .insert((GimmickItemSpawned(tag), tag))
             .id();
 
@@ -168,6 +184,7 @@ fn spawn_item_system(
         commands.entity(field).add_child(gimmick_entity);
 
         scheduler.register(UndoSpawnGimmickEvent { gimmick_entity, item_entity, tag });
+        history.push(PlayerAction::Place { pos: spawn_pos, item: item_entity, tag });
         cursor.reset();
         state.set(StageState::Idle);
     }
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Query, Res, Transform, With, Without};
+use itertools::Itertools;
+
+use crate::error::GameResult;
+use crate::loader::json::{GimmickProperties, ItemCell, PageJson, StageCell, StageJson};
+use crate::page::page_index::PageIndex;
+use crate::simulation::board::{Board, BoardState};
+use crate::simulation::grid::GridPos;
+use crate::stage::playing::gimmick::{Gimmick, GimmickItem, GimmickItemSpawned};
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::phase::moving::key::KeyCounter;
+
+/// Reads the running stage back into the simulation, so the solver can be asked about the current position.
+///
+/// Only meaningful while the player rests on a cell, i.e. outside of `StageState::Moving`.
+#[derive(SystemParam)]
+pub struct RuntimeBoard<'w, 's> {
+    stage: Res<'w, StageJson>,
+    page_index: Res<'w, PageIndex>,
+    key_counter: Res<'w, KeyCounter>,
+    cells: Query<'w, 's, (&'static Transform, &'static GimmickTag, &'static PageIndex, Option<&'static GimmickProperties>), (With<Gimmick>, Without<GimmickItemSpawned>)>,
+    spawned: Query<'w, 's, (&'static Transform, &'static GimmickItemSpawned, Option<&'static GimmickProperties>)>,
+    items: Query<'w, 's, (&'static Transform, &'static GimmickItem, &'static PageIndex, Option<&'static GimmickProperties>)>,
+}
+
+
+impl<'w, 's> RuntimeBoard<'w, 's> {
+    /// The board as it stands now, with the player starting from its current cell.
+    ///
+    /// Picked keys and opened locks are gone, ice boxes sit where they slid to and
+    /// only the unused items are left in the item areas.
+    pub fn board(&self) -> GameResult<(Board, BoardState)> {
+        let player = self
+            .spawned
+            .iter()
+            .find(|(_, GimmickItemSpawned(tag), _)| *tag == GimmickTag::Player)
+            .map(|(transform, ..)| StageCell::from_pixel(transform.translation.truncate(), Vec::new()));
+        let stage = StageJson {
+            pages: (0..self.stage.pages.len())
+                .map(|page_index| self.page(page_index, player.as_ref()))
+                .collect(),
+            ..self.stage.as_ref().clone()
+        };
+
+        let board = Board::new(&stage)?;
+        let mut state = board.initial_state();
+        state.carry(
+            self.key_counter.iter().map(|(color, count)| (color.0, count)),
+            self.spawned
+                .iter()
+                .filter(|(_, GimmickItemSpawned(tag), _)| *tag != GimmickTag::Player)
+                .map(|(transform, GimmickItemSpawned(tag), properties)| (
+                    GridPos::from_cell(&StageCell::from_pixel(transform.translation.truncate(), Vec::new())),
+                    *tag,
+                    properties.copied().unwrap_or_default()
+                )),
+        );
+        Ok((board, state))
+    }
+
+
+    fn page(&self, page_index: usize, player: Option<&StageCell>) -> PageJson {
+        let player = player
+            .filter(|_| page_index == **self.page_index)
+            .map(|cell| (cell.clone(), GimmickTag::Player, None));
+        let mut cells = BTreeMap::<(u8, u8), StageCell>::new();
+
+        for (cell, tag, properties) in self
+            .cells
+            .iter()
+            .filter(|(_, _, idx, _)| ***idx == page_index)
+            .map(|(transform, tag, _, properties)| (StageCell::from_pixel(transform.translation.truncate(), Vec::new()), *tag, properties.copied()))
+            .chain(player)
+        {
+            let cell = cells
+                .entry((cell.row, cell.col))
+                .or_insert(cell);
+
+            cell.tags.push(tag);
+            if let Some(properties) = properties.filter(|properties| !properties.is_empty()) {
+                cell.properties.insert(tag, properties);
+            }
+        }
+
+        PageJson {
+            cells: cells.into_values().collect(),
+            items: self
+                .items
+                .iter()
+                .filter(|(.., idx, _)| ***idx == page_index)
+                .map(|(transform, GimmickItem(tag), _, properties)| ItemCell {
+                    row: ItemCell::row_from_pixel(transform.translation.y),
+                    tag: *tag,
+                    properties: properties.copied().unwrap_or_default(),
+                })
+                .sorted_by_key(|item| item.row)
+                .collect(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use crate::loader::{StageLoadable, StageLoader};
+    use crate::simulation::board::Board;
+    use crate::stage::playing::runtime_board::RuntimeBoard;
+    use crate::stage::tests::new_stage_app;
+
+    #[test]
+    fn read_back_built_in_stages() {
+        for stage in StageLoader::built_in().load().unwrap().into_iter().map(Result::unwrap) {
+            let mut app = new_stage_app(stage.clone());
+            let mut system_state = SystemState::<RuntimeBoard>::new(&mut app.world);
+            let (board, state) = system_state.get(&app.world).board().unwrap();
+
+            let expected = Board::new(&stage).unwrap();
+            assert_eq!(board, expected, "{}", stage.name);
+            assert_eq!(state, expected.initial_state(), "{}", stage.name);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use bevy::prelude::Component;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+
+use crate::simulation::board::{Board, BoardState};
+use crate::simulation::solver::{solve, Solve};
+
+/// A solver run on the async compute pool, so that a search of up to
+/// [`MAX_STATES`](crate::simulation::solver::MAX_STATES) states doesn't stall the frame.
+///
+/// Despawning the entity that holds it cancels the search.
+#[derive(Component)]
+pub struct SolveTask(Task<Solve>);
+
+
+impl SolveTask {
+    pub fn new(board: Board, state: BoardState) -> Self {
+        Self(AsyncComputeTaskPool::get().spawn(async move { solve(&board, &state) }))
+    }
+
+
+    /// The result once the search has finished.
+    #[inline]
+    pub fn poll(&mut self) -> Option<Solve> {
+        future::block_on(future::poll_once(&mut self.0))
+    }
+}
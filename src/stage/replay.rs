@@ -0,0 +1,297 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin, Update};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Commands, Entity, EventWriter, in_state, IntoSystemConfigs, OnEnter, OnExit, Or, Query, Res, ResMut, Resource, Time, Transform, With};
+use bevy_undo2::prelude::UndoRequester;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GameError, GameResult};
+use crate::gama_state::GameState;
+use crate::loader::json::{ItemCell, StageCell, StageJson};
+use crate::page::page_index::PageIndex;
+use crate::stage::history::ActionHistory;
+use crate::stage::playing::gimmick::{Floor, GimmickItem, GimmickItemSpawned};
+use crate::stage::playing::move_direction::MoveDirection;
+use crate::stage::playing::phase::picked_item::SpawnGimmickEvent;
+use crate::stage::playing::phase::start_move::StartMoveEvent;
+use crate::stage::state::StageState;
+use crate::stage::stats::StageStats;
+
+pub const REPLAY_VERSION: u32 = 1;
+
+
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct ReplayPlugin;
+
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ReplayRecorder>()
+            .add_systems(OnEnter(GameState::Stage), start_replay_system)
+            .add_systems(OnExit(GameState::Stage), save_replay_system)
+            .add_systems(Update, play_replay_system.run_if(in_state(GameState::Stage).and_then(in_state(StageState::Idle))));
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplayJson {
+    pub version: u32,
+    pub stage_name: String,
+    /// [`stage_hash`] of the stage the replay was recorded on.
+    pub stage_hash: String,
+    pub events: Vec<ReplayEvent>,
+}
+
+
+impl ReplayJson {
+    /// Fails when the stage has changed since the replay was recorded.
+    pub fn check_stage(&self, stage: &StageJson) -> GameResult {
+        if self.stage_hash == stage_hash(stage) {
+            Ok(())
+        } else {
+            Err(GameError::ReplayStageChanged(self.stage_name.clone()))
+        }
+    }
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct ReplayEvent {
+    /// Seconds since the stage started.
+    pub time: f32,
+    #[serde(flatten)]
+    pub action: ReplayAction,
+}
+
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ReplayAction {
+    Move {
+        direction: MoveDirection,
+    },
+
+    /// The item in `item_row` of the page's item area placed on the cell at `col`, `row`.
+    Place {
+        page: usize,
+        item_row: u8,
+        col: u8,
+        row: u8,
+    },
+
+    Undo,
+}
+
+
+/// FNV-1a of the stage's JSON, stable across builds unlike `DefaultHasher`.
+pub fn stage_hash(stage: &StageJson) -> String {
+    let json = serde_json::to_string(stage).unwrap_or_default();
+    let hash = json
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+    format!("{hash:016x}")
+}
+
+
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct ReplayRecorder {
+    events: Vec<ReplayEvent>,
+    started: Duration,
+    /// Off while a replay is playing back.
+    enabled: bool,
+}
+
+
+#[derive(SystemParam)]
+pub struct ReplayRecord<'w> {
+    recorder: ResMut<'w, ReplayRecorder>,
+    time: Res<'w, Time>,
+}
+
+
+impl<'w> ReplayRecord<'w> {
+    pub fn record(&mut self, action: ReplayAction) {
+        if !self.recorder.enabled {
+            return;
+        }
+        let time = self.time.elapsed().saturating_sub(self.recorder.started).as_secs_f32();
+        self.recorder.events.push(ReplayEvent { time, action });
+    }
+}
+
+
+/// Feeds a replay back through the same events the player would send.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct ReplayPlayback {
+    events: VecDeque<ReplayEvent>,
+    started: Duration,
+}
+
+
+impl ReplayPlayback {
+    pub fn new(replay: ReplayJson) -> Self {
+        Self {
+            events: replay.events.into(),
+            started: Duration::ZERO,
+        }
+    }
+}
+
+
+fn start_replay_system(
+    mut recorder: ResMut<ReplayRecorder>,
+    mut playback: Option<ResMut<ReplayPlayback>>,
+    time: Res<Time>,
+) {
+    *recorder = ReplayRecorder {
+        events: Vec::new(),
+        started: time.elapsed(),
+        enabled: playback.is_none(),
+    };
+    if let Some(playback) = playback.as_mut() {
+        playback.started = time.elapsed();
+    }
+}
+
+
+fn save_replay_system(
+    mut commands: Commands,
+    mut recorder: ResMut<ReplayRecorder>,
+    stage: Res<StageJson>,
+) {
+    commands.remove_resource::<ReplayPlayback>();
+    if !recorder.enabled || recorder.events.is_empty() {
+        return;
+    }
+
+    let replay = ReplayJson {
+        version: REPLAY_VERSION,
+        stage_name: stage.name.clone(),
+        stage_hash: stage_hash(&stage),
+        events: std::mem::take(&mut recorder.events),
+    };
+    if let Err(e) = write_replay(&replay) {
+        bevy::log::warn!("replay could not be saved: {e}");
+    }
+}
+
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_replay(replay: &ReplayJson) -> GameResult {
+    let dir = crate::loader::native::data_dir()?.join("replays");
+    std::fs::create_dir_all(&dir)?;
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    std::fs::write(dir.join(format!("{}-{secs}.replay.json", replay.stage_name)), serde_json::to_string(replay)?)?;
+    Ok(())
+}
+
+
+/// The browser keeps only the latest replay of each stage.
+#[cfg(target_arch = "wasm32")]
+fn write_replay(replay: &ReplayJson) -> GameResult {
+    use crate::loader::web::{js_error, local_storage};
+
+    local_storage()?
+        .set_item(&format!("eskate.replay.{}", replay.stage_name), &serde_json::to_string(replay)?)
+        .map_err(js_error)
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn play_replay_system(
+    mut playback: Option<ResMut<ReplayPlayback>>,
+    mut requester: UndoRequester,
+    mut history: ResMut<ActionHistory>,
+    mut stats: ResMut<StageStats>,
+    mut move_writer: EventWriter<StartMoveEvent>,
+    mut spawn_writer: EventWriter<SpawnGimmickEvent>,
+    items: Query<(Entity, &Transform, &PageIndex, &GimmickItem)>,
+    floors: Query<(&Transform, &PageIndex), Or<(With<Floor>, With<GimmickItemSpawned>)>>,
+    time: Res<Time>,
+) {
+    let Some(playback) = playback.as_mut() else { return; };
+    let elapsed = time.elapsed().saturating_sub(playback.started).as_secs_f32();
+    if playback.events.front().map_or(true, |event| elapsed < event.time) {
+        return;
+    }
+    let Some(ReplayEvent { action, .. }) = playback.events.pop_front() else { return; };
+
+    match action {
+        ReplayAction::Move { direction } => {
+            history.start_move(direction);
+            move_writer.send(StartMoveEvent(direction));
+        }
+        ReplayAction::Place { page, item_row, col, row } => {
+            let item = items
+                .iter()
+                .find(|(_, transform, idx, _)| idx.0 == page && ItemCell::row_from_pixel(transform.translation.y) == item_row);
+            let floor = floors
+                .iter()
+                .filter(|(_, idx)| idx.0 == page)
+                .map(|(transform, _)| transform.translation)
+                .find(|pos| {
+                    let cell = StageCell::from_pixel(pos.truncate(), Vec::new());
+                    cell.col == col && cell.row == row
+                });
+            if let (Some((item, _, _, GimmickItem(tag))), Some(pos)) = (item, floor) {
+                spawn_writer.send(SpawnGimmickEvent(pos, item, *tag));
+            }
+        }
+        ReplayAction::Undo => {
+            if history.undo() {
+                stats.undos += 1;
+                requester.undo();
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::loader::json::StageJson;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::replay::{REPLAY_VERSION, ReplayAction, ReplayEvent, ReplayJson, stage_hash};
+
+    #[test]
+    fn read_written_replay() {
+        let replay = ReplayJson {
+            version: REPLAY_VERSION,
+            stage_name: "test".to_string(),
+            stage_hash: "0".to_string(),
+            events: vec![
+                ReplayEvent { time: 0.5, action: ReplayAction::Move { direction: MoveDirection::Left } },
+                ReplayEvent { time: 1.5, action: ReplayAction::Place { page: 0, item_row: 1, col: 2, row: 3 } },
+                ReplayEvent { time: 2., action: ReplayAction::Undo },
+            ],
+        };
+
+        let json = serde_json::to_string(&replay).unwrap();
+        assert_eq!(serde_json::from_str::<ReplayJson>(&json).unwrap(), replay);
+    }
+
+
+    #[test]
+    fn hash_changes_with_stage() {
+        let stage = stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]);
+        let renamed = StageJson {
+            name: "other".to_string(),
+            ..stage.clone()
+        };
+
+        assert_eq!(stage_hash(&stage), stage_hash(&stage.clone()));
+        assert_ne!(stage_hash(&stage), stage_hash(&renamed));
+    }
+}
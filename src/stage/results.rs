@@ -0,0 +1,375 @@
+use std::time::Duration;
+
+use bevy::app::{App, Plugin, Update};
+use bevy::core::Name;
+use bevy::hierarchy::BuildChildren;
+use bevy::prelude::{AlignItems, ButtonBundle, ChildBuilder, Color, Commands, Component, Entity, in_state, IntoSystemConfigs, JustifyContent, NextState, NodeBundle, OnEnter, PositionType, Query, Res, ResMut, Resource, Text, TextBundle, TextStyle, Val, With};
+use bevy::ui::{BackgroundColor, FlexDirection, Interaction, Style, UiRect, ZIndex};
+use bevy::utils::default;
+
+use crate::assets::font::FontAssets;
+use crate::assets::stage::{BuiltInStage, BuiltInStages};
+use crate::extension::InteractionCondition;
+use crate::gama_state::GameState;
+use crate::loader::json::StageJson;
+use crate::page::page_count::PageCount;
+use crate::progress::{Progress, StageId, StageRecord, StageResult};
+use crate::simulation::board::Board;
+use crate::stage::playing::solve_task::SolveTask;
+use crate::stage::state::StageState;
+
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StageResultsPlugin;
+
+
+impl Plugin for StageResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(StageState::Goaled), spawn_results.run_if(in_state(GameState::Stage)))
+            .add_systems(Update, (
+                results_button_system,
+                show_par_system
+            ).run_if(in_state(GameState::Stage).and_then(in_state(StageState::Goaled))));
+    }
+}
+
+
+/// The clear that was just made, with the record as it stood before it.
+#[derive(Resource, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StageClear {
+    pub result: StageResult,
+    pub previous_best: Option<StageRecord>,
+}
+
+
+/// The search for the stage's par; the moves line gains it once the search is done.
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+struct ParSearch;
+
+
+#[derive(Component, Debug, Copy, Clone, Eq, PartialEq)]
+struct MovesText;
+
+
+#[derive(Component, Debug, Clone, PartialEq)]
+enum ResultsButton {
+    Retry,
+    Next(BuiltInStage),
+    Back,
+}
+
+
+fn spawn_results(
+    mut commands: Commands,
+    font: Res<FontAssets>,
+    stage: Res<StageJson>,
+    stage_id: Option<Res<StageId>>,
+    clear: Res<StageClear>,
+    stages: Res<BuiltInStages>,
+    progress: Res<Progress>,
+) {
+    if let Ok(board) = Board::new(&stage) {
+        let state = board.initial_state();
+        commands.spawn((ParSearch, SolveTask::new(board, state)));
+    }
+    let next = stage_id
+        .and_then(|stage_id| stages.next_stage(&stage_id, &progress))
+        .cloned();
+    let lines = result_lines(&clear, None);
+
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: BackgroundColor::from(Color::rgba(0., 0., 0., 0.6)),
+        z_index: ZIndex::Global(10),
+        ..default()
+    })
+        .insert(Name::new("Results"))
+        .with_children(|parent| {
+            parent.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.),
+                    padding: UiRect::all(Val::Px(24.)),
+                    ..default()
+                },
+                background_color: BackgroundColor::from(Color::rgba(0.1, 0.1, 0.2, 0.9)),
+                ..default()
+            })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Clear!", text_style(&font, 48.)));
+                    for (i, line) in lines.into_iter().enumerate() {
+                        let mut text = parent.spawn(TextBundle::from_section(line, text_style(&font, 28.)));
+                        if i == 0 {
+                            text.insert(MovesText);
+                        }
+                    }
+
+                    parent.spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(12.),
+                            margin: UiRect::top(Val::Px(12.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                        .with_children(|parent| {
+                            spawn_button(parent, &font, "Retry", ResultsButton::Retry);
+                            if let Some(next) = next {
+                                spawn_button(parent, &font, "Next", ResultsButton::Next(next));
+                            }
+                            spawn_button(parent, &font, "Back", ResultsButton::Back);
+                        });
+                });
+        });
+}
+
+
+fn show_par_system(
+    mut commands: Commands,
+    mut searches: Query<(Entity, &mut SolveTask), With<ParSearch>>,
+    mut moves_text: Query<&mut Text, With<MovesText>>,
+    clear: Res<StageClear>,
+) {
+    for (search, mut task) in searches.iter_mut() {
+        let Some(solve) = task.poll() else { continue; };
+        commands.entity(search).despawn();
+        let par = solve.solution().map(|solution| solution.moves());
+        for mut text in moves_text.iter_mut() {
+            text.sections[0].value = result_lines(&clear, par).remove(0);
+        }
+    }
+}
+
+
+/// The first line is the moves, which [`show_par_system`] rewrites once the par is known.
+fn result_lines(clear: &StageClear, par: Option<usize>) -> Vec<String> {
+    let StageClear { result, previous_best } = clear;
+    let compare = |best: Option<String>| best.map(|best| format!("  (best {best})")).unwrap_or_default();
+
+    let mut lines = vec![
+        format!(
+            "Moves: {}{}{}",
+            result.moves,
+            par.map(|par| format!("  (par {par})")).unwrap_or_default(),
+            compare(previous_best.map(|best| best.best_moves.to_string()))
+        ),
+        format!("Undos: {}{}", result.undos, compare(previous_best.map(|best| best.fewest_undos.to_string()))),
+        format!("Time: {}{}", format_time(result.time), compare(previous_best.map(|best| format_time(best.best_time)))),
+    ];
+    if 0 < result.hints {
+        lines.push(format!("Hints: {}", result.hints));
+    }
+    lines
+}
+
+
+fn format_time(time: Duration) -> String {
+    let secs = time.as_secs_f32();
+    format!("{}:{:04.1}", (secs / 60.) as u32, secs % 60.)
+}
+
+
+#[inline]
+fn text_style(font: &FontAssets, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: font.button_text.clone(),
+        font_size,
+        color: Color::WHITE,
+    }
+}
+
+
+fn spawn_button(parent: &mut ChildBuilder, font: &FontAssets, label: &str, button: ResultsButton) {
+    parent.spawn(ButtonBundle {
+        style: Style {
+            width: Val::Px(140.),
+            height: Val::Px(50.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: BackgroundColor::from(Color::from([80. / 255., 150. / 255., 250. / 255., 0.8])),
+        ..default()
+    })
+        .insert((Name::new(label.to_string()), button))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, TextStyle {
+                color: Color::BLACK,
+                ..text_style(font, 28.)
+            }));
+        });
+}
+
+
+fn results_button_system(
+    mut commands: Commands,
+    mut state: ResMut<NextState<GameState>>,
+    buttons: Query<(&Interaction, &ResultsButton)>,
+) {
+    let Some((_, button)) = buttons.iter().find(|(interaction, _)| interaction.pressed()) else { return; };
+
+    match button {
+        ResultsButton::Retry => state.set(GameState::StageRestart),
+        ResultsButton::Next(next) => {
+            commands.insert_resource(next.stage_id());
+            commands.insert_resource(next.stage.clone());
+            commands.insert_resource(PageCount::new(next.stage.pages.len()));
+            state.set(GameState::StageRestart);
+        }
+        ResultsButton::Back => state.set(GameState::StageSelect),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::app::App;
+    use bevy::ecs::event::ManualEventReader;
+    use bevy::prelude::{State, Text, With};
+    use bevy::ui::Interaction;
+
+    use crate::assets::stage::{BuiltInStage, BuiltInStages, StagePack, UnlockRule};
+    use crate::loader::json::StageJson;
+    use crate::progress::{Progress, StageId, StageRecord, StageResult};
+    use crate::simulation::action::Action;
+    use crate::simulation::tests::{page, stage};
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage::results::{format_time, MovesText, ParSearch, result_lines, ResultsButton, StageClear};
+    use crate::stage::state::StageState;
+    use crate::stage::tests::{new_stage_app, play, player_translation};
+
+    #[test]
+    fn format_minutes_and_seconds() {
+        assert_eq!(format_time(Duration::from_millis(83_400)), "1:23.4");
+        assert_eq!(format_time(Duration::from_millis(5_000)), "0:05.0");
+    }
+
+
+    #[test]
+    fn compare_with_par_and_best() {
+        let clear = StageClear {
+            result: StageResult { moves: 12, undos: 1, hints: 0, time: Duration::from_secs(42) },
+            previous_best: Some(StageRecord { clears: 1, best_moves: 10, fewest_undos: 0, best_time: Duration::from_secs(30) }),
+        };
+
+        assert_eq!(result_lines(&clear, Some(9)), vec![
+            "Moves: 12  (par 9)  (best 10)".to_string(),
+            "Undos: 1  (best 0)".to_string(),
+            "Time: 0:42.0  (best 0:30.0)".to_string(),
+        ]);
+    }
+
+
+    #[test]
+    fn list_hints_when_used() {
+        let clear = StageClear {
+            result: StageResult { moves: 3, undos: 0, hints: 2, time: Duration::from_secs(5) },
+            previous_best: None,
+        };
+
+        assert_eq!(result_lines(&clear, None).last(), Some(&"Hints: 2".to_string()));
+    }
+
+
+    #[test]
+    fn show_par_once_searched() {
+        let mut app = goaled_app();
+        for _ in 0..1000 {
+            if app.world.query_filtered::<(), With<ParSearch>>().iter(&app.world).next().is_none() {
+                break;
+            }
+            app.update();
+        }
+
+        let text = app.world.query_filtered::<&Text, With<MovesText>>().single(&app.world);
+        assert_eq!(text.sections[0].value, "Moves: 1  (par 1)");
+    }
+
+
+    #[test]
+    fn retry_rebuilds_stage() {
+        let mut app = goaled_app();
+        let start = player_translation(&mut app);
+
+        press_button(&mut app, |button| *button == ResultsButton::Retry);
+
+        assert_eq!(app.world.resource::<StageJson>().name, "first");
+        assert_eq!(player_translation(&mut app), start);
+        assert_eq!(*app.world.resource::<State<StageState>>().get(), StageState::Idle);
+        assert_eq!(app.world.query::<&ResultsButton>().iter(&app.world).count(), 0);
+    }
+
+
+    #[test]
+    fn next_builds_next_stage() {
+        let mut app = goaled_app();
+        let start = player_translation(&mut app);
+
+        press_button(&mut app, |button| matches!(button, ResultsButton::Next(_)));
+
+        assert_eq!(app.world.resource::<StageJson>().name, "second");
+        assert_eq!(*app.world.resource::<StageId>(), StageId::built_in("second"));
+        assert!(app.world.resource::<Progress>().is_cleared(&StageId::built_in("first")));
+        assert_ne!(player_translation(&mut app), start);
+        assert_eq!(*app.world.resource::<State<StageState>>().get(), StageState::Idle);
+        assert_eq!(app.world.query::<&ResultsButton>().iter(&app.world).count(), 0);
+    }
+
+
+    /// Clears the first of two built-in stages, so that the results show Retry and Next.
+    fn goaled_app() -> App {
+        let first = named_stage("first", &["####", "#PG#", "####"]);
+        let second = named_stage("second", &["#####", "#.PG#", "#####"]);
+        let mut app = new_stage_app(first.clone());
+        app.insert_resource(BuiltInStages(vec![StagePack {
+            id: "pack".to_string(),
+            name: "pack".to_string(),
+            unlock: UnlockRule::Always,
+            stages: [first, second]
+                .into_iter()
+                .map(|stage| BuiltInStage { id: stage.name.clone(), name: stage.name.clone(), stage })
+                .collect(),
+        }]));
+        app.insert_resource(StageId::built_in("first"));
+        let start = player_translation(&mut app);
+
+        assert!(play(&mut app, &mut ManualEventReader::default(), Action::Move(MoveDirection::Right)));
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(*app.world.resource::<State<StageState>>().get(), StageState::Goaled);
+        assert_ne!(player_translation(&mut app), start);
+        app
+    }
+
+
+    fn named_stage(name: &str, rows: &[&str]) -> StageJson {
+        StageJson {
+            name: name.to_string(),
+            ..stage(vec![page(rows, &[])])
+        }
+    }
+
+
+    fn press_button(app: &mut App, find: impl Fn(&ResultsButton) -> bool) {
+        let mut buttons = app.world.query::<(&mut Interaction, &ResultsButton)>();
+        let (mut interaction, _) = buttons
+            .iter_mut(&mut app.world)
+            .find(|(_, button)| find(button))
+            .unwrap();
+        *interaction = Interaction::Pressed;
+        for _ in 0..4 {
+            app.update();
+        }
+    }
+}
@@ -8,5 +8,6 @@ pub enum StageState {
     NextPage,
     Goaled,
     PickedItem,
-    Undo
+    Undo,
+    Fail
 }
\ No newline at end of file
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use bevy::prelude::{ResMut, Resource};
+
+use crate::progress::StageResult;
+
+/// Counts for the stage being played, reset when it starts.
+#[derive(Resource, Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StageStats {
+    pub moves: usize,
+    pub undos: usize,
+    pub hints: usize,
+    /// [`bevy::time::Time::elapsed`] when the stage started.
+    pub started: Duration,
+}
+
+
+impl StageStats {
+    #[inline]
+    pub const fn new(started: Duration) -> Self {
+        Self {
+            moves: 0,
+            undos: 0,
+            hints: 0,
+            started,
+        }
+    }
+
+
+    #[inline]
+    pub fn result(&self, now: Duration) -> StageResult {
+        StageResult {
+            moves: self.moves,
+            undos: self.undos,
+            hints: self.hints,
+            time: now.saturating_sub(self.started),
+        }
+    }
+}
+
+
+pub fn count_move_system(mut stats: ResMut<StageStats>) {
+    stats.moves += 1;
+}
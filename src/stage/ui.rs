@@ -1,12 +1,18 @@
 use bevy::hierarchy::BuildChildren;
-use bevy::prelude::{Commands, default, NodeBundle, Text, TextBundle, TextStyle, Transform, Val};
-use bevy::ui::{Style, UiRect};
+use bevy::prelude::{Color, Commands, default, NodeBundle, Text, TextBundle, TextStyle, Transform, Val};
+use bevy::ui::{FlexDirection, Style, UiRect};
+use itertools::Itertools;
+
 use crate::assets::font::FontAssets;
-use crate::stage::KeysCountText;
+use crate::loader::json::StageJson;
+use crate::stage::{HintsText, KeysCountText};
+use crate::stage::playing::gimmick::key::KeyColor;
+use crate::stage::playing::gimmick::tag::GimmickTag;
 
 pub fn spawn_ui(
     commands: &mut Commands,
-    fonts: &FontAssets
+    fonts: &FontAssets,
+    stage: &StageJson,
 ) {
     commands
         .spawn(NodeBundle {
@@ -14,20 +20,63 @@ pub fn spawn_ui(
                 width: Val::Percent(100.),
                 height: Val::Percent(100.),
                 padding: UiRect::all(Val::Px(10.)),
+                flex_direction: FlexDirection::Column,
                 ..default()
             },
             ..default()
         })
         .with_children(|parent| {
-            parent.spawn(TextBundle {
-                text: Text::from_section("Keys: 0", TextStyle {
-                    font: fonts.button_text.clone(),
-                    font_size: 32.,
+            for key_color in key_colors(stage) {
+                parent.spawn(TextBundle {
+                    text: Text::from_section("Key: 0", TextStyle {
+                        font: fonts.button_text.clone(),
+                        font_size: 32.,
+                        color: key_color.color(),
+                    }),
+                    transform: Transform::from_xyz(-540., 120., 0.),
                     ..default()
-                }),
-                transform: Transform::from_xyz(-540., 120., 0.),
-                ..default()
-            })
-                .insert(KeysCountText);
+                })
+                    .insert(KeysCountText(key_color));
+            }
+            parent.spawn(TextBundle::from_section(hints_text(stage.hint_budget()), TextStyle {
+                font: fonts.button_text.clone(),
+                font_size: 24.,
+                color: Color::WHITE,
+            }))
+                .insert(HintsText);
         });
-}
\ No newline at end of file
+}
+
+
+#[inline]
+pub fn hints_text(remaining: usize) -> String {
+    format!("Hints (H): {remaining}")
+}
+
+
+/// Colours of every key and lock in the stage; the default colour is always shown.
+fn key_colors(stage: &StageJson) -> Vec<KeyColor> {
+    let cells = stage
+        .pages
+        .iter()
+        .flat_map(|page| page.cells.iter())
+        .flat_map(|cell| cell
+            .tags
+            .iter()
+            .map(move |tag| (*tag, cell.properties(*tag))));
+
+    let items = stage
+        .pages
+        .iter()
+        .flat_map(|page| page.items.iter())
+        .map(|item| (item.tag, item.properties));
+
+    cells
+        .chain(items)
+        .filter(|(tag, _)| matches!(tag, GimmickTag::Key | GimmickTag::Lock))
+        .map(|(_, properties)| KeyColor(properties.channel.unwrap_or_default()))
+        .chain([KeyColor::default()])
+        .sorted()
+        .dedup()
+        .collect()
+}
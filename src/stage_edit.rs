@@ -11,6 +11,7 @@ use crate::stage_edit::eraser::StageEditEraserPlugin;
 use crate::stage_edit::idle::StageEditIdlePlugin;
 use crate::stage_edit::page::spawn_page;
 use crate::stage_edit::pick::StageEditPickedPlugin;
+use crate::stage_edit::property::StageEditPropertyPlugin;
 use crate::stage_edit::save::StageEditSavePlugin;
 use crate::stage_edit::stage_name::StageName;
 use crate::stage_edit::ui::{gimmick_sprite_bundle, spawn_ui};
@@ -32,6 +33,7 @@ pub mod stage_name;
 pub mod ui;
 mod eraser;
 pub mod page;
+mod property;
 
 
 #[derive(Default, Debug, PartialEq, Copy, Clone)]
@@ -47,7 +49,8 @@ impl Plugin for StageEditPlugin {
                 StageEditIdlePlugin,
                 StageEditPickedPlugin,
                 StageEditSavePlugin,
-                StageEditEraserPlugin
+                StageEditEraserPlugin,
+                StageEditPropertyPlugin
             ));
     }
 }
@@ -65,7 +68,7 @@ fn setup(
     commands.insert_resource(PageCount::new(stage.pages.len()));
 
     for (page_index, page) in stage.pages.iter().enumerate() {
-        spawn_page(&mut commands, page, PageIndex::new(page_index), &assets);
+        spawn_page(&mut commands, &stage, page, PageIndex::new(page_index), &assets);
     }
     spawn_ui(&mut commands, &assets, &edit_assets);
 }
@@ -88,7 +91,7 @@ mod tests {
         app.init_resource::<StageEditStatus>();
         app.init_resource::<PageIndex>();
         app.init_resource::<GimmickAssets>();
-        app.insert_resource(StageJson::empty_stage(page_count, 15, 25, Vec2::ZERO));
+        app.insert_resource(StageJson::empty_stage(page_count, 15, 25));
         app.add_event::<UserInputEvent>();
         app.add_state::<GameState>();
         app.insert_resource(StageEditAssets::default());
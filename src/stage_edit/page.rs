@@ -1,11 +1,10 @@
 use bevy::hierarchy::{BuildChildren, ChildBuilder};
-use bevy::math::Vec3;
 use bevy::prelude::{Commands, Component, default, Transform};
 use bevy::sprite::{Anchor, Sprite, SpriteBundle};
 use itertools::Itertools;
 
 use crate::assets::gimmick::GimmickAssets;
-use crate::loader::json::PageJson;
+use crate::loader::json::{ITEM_AREA_WIDTH, PageJson, StageJson};
 use crate::page::page_index::PageIndex;
 use crate::stage_edit::page::item_area::spawn_item_area;
 
@@ -21,25 +20,27 @@ pub struct Field;
 
 pub fn spawn_page(
     commands: &mut Commands,
+    stage: &StageJson,
     page: &PageJson,
     page_index: PageIndex,
     gimmick_assets: &GimmickAssets,
 ) {
+    let origin = stage.page_origin(*page_index);
     commands
         .spawn(SpriteBundle {
             sprite: Sprite {
                 anchor: Anchor::TopLeft,
                 ..default()
             },
-            transform: Transform::from_xyz(page.x, page.y, 0.),
+            transform: Transform::from_xyz(origin.x, origin.y, 0.),
             ..default()
         })
         .insert((Page, page_index))
         .with_children(|parent| {
-            spawn_item_area(parent, gimmick_assets,&page.item_area, page_index);
+            spawn_item_area(parent, gimmick_assets, page, page_index);
             parent
                 .spawn(SpriteBundle {
-                    transform: Transform::from_xyz(page.item_area.width, 0., 0.),
+                    transform: Transform::from_xyz(ITEM_AREA_WIDTH, 0., 0.),
                     ..default()
                 })
                 .insert((Field, page_index))
@@ -58,7 +59,7 @@ fn spawn_page_gimmicks(
 ) {
     for cell in page.cells.iter() {
         for (index, tag) in cell.tags.iter().sorted().enumerate() {
-            tag.spawn_with_parent(parent, assets, Vec3::new(cell.x, cell.y, index as f32), page_index);
+            tag.spawn_with_parent(parent, assets, cell.pixel().extend(index as f32), page_index, cell.properties(*tag));
         }
     }
 }
@@ -8,11 +8,13 @@ use crate::assets::gimmick::GimmickAssets;
 use crate::assets::stage_edit_assets::StageEditAssets;
 use crate::button::{SpriteButton, SpriteInteraction};
 use crate::gama_state::GameState;
+use crate::loader::json::ITEM_GAP;
 use crate::page::page_index::PageIndex;
 use crate::stage::playing::gimmick::{Floor, Gimmick, GIMMICK_HEIGHT, GimmickItem, GimmickItemSpawned};
 use crate::stage_edit::{gimmick_sprite_bundle, StageEditStatus};
 use crate::stage_edit::idle::OnPick;
 use crate::stage_edit::page::item_area::ItemPlusButton;
+use crate::stage_edit::property::SelectedGimmick;
 
 #[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
 pub struct StageEditPickedPlugin;
@@ -88,6 +90,7 @@ fn spawn_gimmick_system(
 
             let parent = parent.get();
             commands.entity(parent).add_child(gimmick);
+            commands.insert_resource(SelectedGimmick(gimmick));
             scheduler.register(UndoCallbackEvent::new(move |cmd| {
                 cmd.entity(parent).remove_children(&[gimmick]);
             }));
@@ -114,24 +117,27 @@ fn add_item_system(
         .and_then(|children| children
             .iter()
             .filter_map(|c| items.get(*c).ok())
-            .map(|ct| ct.translation + Vec3::new(0., -GIMMICK_HEIGHT - 8., 0.))
+            .map(|ct| ct.translation + Vec3::new(0., -GIMMICK_HEIGHT - ITEM_GAP, 0.))
             .sorted_by(|p1, p2| p2.y.partial_cmp(&p1.y).unwrap())
             .last()
         )
         .unwrap_or(Vec3::NEG_Y * GIMMICK_HEIGHT);
 
-    commands
-        .entity(item_area_entity)
-        .with_children(|parent| {
-            let item = parent.spawn(gimmick_sprite_bundle(pos + Vec3::Z, tag.image(&assets)))
-                .insert(GimmickItem(*tag))
-                .insert(*page_index)
-                .id();
-
-            scheduler.register(UndoCallbackEvent::new(move |cmd| {
-                cmd.entity(item).despawn();
-            }));
-        });
+    let item = commands
+        .spawn(gimmick_sprite_bundle(pos + Vec3::Z, tag.image(&assets)))
+        .insert((
+            SpriteButton,
+            SpriteInteraction::None,
+            GimmickItem(*tag),
+            *page_index
+        ))
+        .id();
+
+    commands.entity(item_area_entity).add_child(item);
+    commands.insert_resource(SelectedGimmick(item));
+    scheduler.register(UndoCallbackEvent::new(move |cmd| {
+        cmd.entity(item).despawn();
+    }));
 }
 
 
@@ -0,0 +1,353 @@
+use bevy::app::{App, Plugin, Update};
+use bevy::asset::AssetServer;
+use bevy::core::Name;
+use bevy::input::Input;
+use bevy::prelude::{AnyOf, Color, Commands, Component, Condition, Entity, in_state, IntoSystemConfigs, KeyCode, not, OnEnter, OnExit, PositionType, Query, Res, Resource, resource_exists, resource_exists_and_equals, Sprite, Text, TextBundle, TextStyle, Transform, UiImage, Val, With};
+use bevy::ui::Style;
+use bevy::utils::default;
+
+use crate::assets::font::FontAssets;
+use crate::assets::gimmick::GimmickAssets;
+use crate::button::SpriteInteraction;
+use crate::cursor::GameCursor;
+use crate::gama_state::GameState;
+use crate::loader::json::{GimmickProperties, PortalTarget, StageCell};
+use crate::page::page_index::PageIndex;
+use crate::stage::playing::gimmick::{Floor, GimmickItem};
+use crate::stage::playing::gimmick::key::KeyColor;
+use crate::stage::playing::gimmick::tag::GimmickTag;
+use crate::stage::playing::move_direction::MoveDirection;
+use crate::stage_edit::eraser::OnPickedEraser;
+use crate::stage_edit::pick::PickedItemsParam;
+use crate::stage_edit::StageEditStatus;
+
+/// The gimmick whose properties the keys change: the one last placed or clicked.
+#[derive(Resource, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SelectedGimmick(pub Entity);
+
+
+/// Set while the next floor clicked becomes the selected portal's target; the page may be changed first.
+#[derive(Resource, Debug, Default, Copy, Clone, Eq, PartialEq)]
+struct PickingTarget;
+
+
+#[derive(Component, Debug, Default, Copy, Clone, Eq, PartialEq)]
+struct PropertyText;
+
+
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct StageEditPropertyPlugin;
+
+
+impl Plugin for StageEditPropertyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(GameState::StageEdit), spawn_property_text)
+            .add_systems(OnExit(GameState::StageEdit), remove_selected)
+            .add_systems(Update, (
+                pick_target_system.run_if(resource_exists::<PickingTarget>()),
+                select_gimmick_system.run_if(not(resource_exists::<PickingTarget>())),
+                edit_property_system,
+                start_picking_target_system,
+                show_property_system
+            )
+                .chain()
+                .run_if(in_state(GameState::StageEdit).and_then(resource_exists_and_equals(StageEditStatus::Idle))));
+    }
+}
+
+
+fn spawn_property_text(
+    mut commands: Commands,
+    font: Res<FontAssets>,
+) {
+    commands.spawn(TextBundle::from_section("", TextStyle {
+        font: font.button_text.clone(),
+        font_size: 20.,
+        color: Color::WHITE,
+    }).with_style(Style {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(10.),
+        left: Val::Px(10.),
+        ..default()
+    }))
+        .insert((Name::new("PropertyText"), PropertyText));
+}
+
+
+fn remove_selected(mut commands: Commands) {
+    commands.remove_resource::<SelectedGimmick>();
+    commands.remove_resource::<PickingTarget>();
+}
+
+
+fn select_gimmick_system(
+    mut commands: Commands,
+    gimmicks: Query<(Entity, &SpriteInteraction, AnyOf<(&GimmickTag, &GimmickItem)>)>,
+) {
+    let selected = gimmicks
+        .iter()
+        .find(|(_, interaction, tags)| interaction.just_pressed() && is_editable(tag_of(*tags)));
+    if let Some((entity, ..)) = selected {
+        commands.insert_resource(SelectedGimmick(entity));
+    }
+}
+
+
+/// The properties are kept on the gimmick, where saving picks them up.
+fn edit_property_system(
+    mut commands: Commands,
+    mut gimmicks: Query<(&mut Transform, &mut Sprite, AnyOf<(&GimmickTag, &GimmickItem)>, Option<&GimmickProperties>)>,
+    selected: Option<Res<SelectedGimmick>>,
+    key: Res<Input<KeyCode>>,
+) {
+    let Some(selected) = selected else { return; };
+    let Ok((mut transform, mut sprite, tags, properties)) = gimmicks.get_mut(selected.0) else { return; };
+    let tag = tag_of(tags);
+    let properties = properties.copied().unwrap_or_default();
+    let edited = edit(tag, properties, &key);
+    if edited == properties {
+        return;
+    }
+
+    if rotates(tag) {
+        transform.rotation = edited.rotation.unwrap_or(MoveDirection::Up).quat();
+    }
+    if has_channel(tag) {
+        sprite.color = KeyColor(edited.channel.unwrap_or_default()).color();
+    }
+    commands.entity(selected.0).insert(edited);
+}
+
+
+/// `T` on a selected portal drops whatever was picked, so that the next click only chooses the target.
+fn start_picking_target_system(
+    mut commands: Commands,
+    mut cursor: Query<&mut UiImage, With<GameCursor>>,
+    picked_items: PickedItemsParam,
+    assets: Res<GimmickAssets>,
+    gimmicks: Query<AnyOf<(&GimmickTag, &GimmickItem)>>,
+    selected: Option<Res<SelectedGimmick>>,
+    key: Res<Input<KeyCode>>,
+) {
+    if !key.just_pressed(KeyCode::T) {
+        return;
+    }
+    let Some(selected) = selected else { return; };
+    if !gimmicks.get(selected.0).is_ok_and(|tags| targets(tag_of(tags))) {
+        return;
+    }
+
+    picked_items.remove_picked(&mut commands);
+    commands.remove_resource::<OnPickedEraser>();
+    commands.insert_resource(PickingTarget);
+    if let Ok(mut cursor) = cursor.get_single_mut() {
+        cursor.texture = assets.portal.clone();
+    }
+}
+
+
+fn pick_target_system(
+    mut commands: Commands,
+    mut cursor: Query<&mut UiImage, With<GameCursor>>,
+    asset_server: Res<AssetServer>,
+    floors: Query<(&Transform, &SpriteInteraction, &PageIndex), With<Floor>>,
+    portals: Query<Option<&GimmickProperties>>,
+    selected: Option<Res<SelectedGimmick>>,
+) {
+    let Some((transform, _, page_index)) = floors.iter().find(|(_, interaction, _)| interaction.just_pressed()) else { return; };
+    commands.remove_resource::<PickingTarget>();
+    if let Ok(mut cursor) = cursor.get_single_mut() {
+        cursor.texture = asset_server.load("game_cursor.png");
+    }
+
+    let Some(selected) = selected else { return; };
+    let Ok(properties) = portals.get(selected.0) else { return; };
+    let cell = StageCell::from_pixel(transform.translation.truncate(), Vec::new());
+    commands.entity(selected.0).insert(GimmickProperties {
+        target: Some(PortalTarget { page: page_index.0, col: cell.col, row: cell.row }),
+        ..properties.copied().unwrap_or_default()
+    });
+}
+
+
+fn show_property_system(
+    mut text: Query<&mut Text, With<PropertyText>>,
+    gimmicks: Query<(AnyOf<(&GimmickTag, &GimmickItem)>, Option<&GimmickProperties>)>,
+    selected: Option<Res<SelectedGimmick>>,
+) {
+    let Ok(mut text) = text.get_single_mut() else { return; };
+    let line = selected
+        .and_then(|selected| gimmicks.get(selected.0).ok())
+        .map(|(tags, properties)| describe(tag_of(tags), &properties.copied().unwrap_or_default()))
+        .unwrap_or_default();
+    if text.sections[0].value != line {
+        text.sections[0].value = line;
+    }
+}
+
+
+#[inline]
+fn tag_of((tag, item): (Option<&GimmickTag>, Option<&GimmickItem>)) -> GimmickTag {
+    tag.copied().unwrap_or_else(|| item.map_or(GimmickTag::Floor, |item| item.0))
+}
+
+
+#[inline]
+fn is_editable(tag: GimmickTag) -> bool {
+    rotates(tag) || has_channel(tag) || counts_keys(tag) || targets(tag)
+}
+
+
+#[inline]
+fn rotates(tag: GimmickTag) -> bool {
+    matches!(tag, GimmickTag::Turn | GimmickTag::Arrow | GimmickTag::Gate)
+}
+
+
+#[inline]
+fn has_channel(tag: GimmickTag) -> bool {
+    matches!(tag, GimmickTag::Key | GimmickTag::Lock | GimmickTag::Teleporter)
+}
+
+
+#[inline]
+fn counts_keys(tag: GimmickTag) -> bool {
+    matches!(tag, GimmickTag::Lock)
+}
+
+
+#[inline]
+fn targets(tag: GimmickTag) -> bool {
+    matches!(tag, GimmickTag::Portal)
+}
+
+
+/// `R` turns the gimmick clockwise, the digit keys choose its channel,
+/// and up and down change how many keys a lock takes.
+fn edit(tag: GimmickTag, mut properties: GimmickProperties, key: &Input<KeyCode>) -> GimmickProperties {
+    if rotates(tag) && key.just_pressed(KeyCode::R) {
+        properties.rotation = Some(properties.rotation.unwrap_or(MoveDirection::Up).clockwise());
+    }
+    if has_channel(tag) {
+        if let Some(channel) = key.get_just_pressed().find_map(digit) {
+            properties.channel = Some(channel);
+        }
+    }
+    if counts_keys(tag) {
+        let require_keys = properties.require_keys.unwrap_or(1);
+        if key.just_pressed(KeyCode::Up) {
+            properties.require_keys = Some(require_keys + 1);
+        } else if key.just_pressed(KeyCode::Down) {
+            properties.require_keys = Some(require_keys.saturating_sub(1).max(1));
+        }
+    }
+    properties
+}
+
+
+fn digit(key: &KeyCode) -> Option<u8> {
+    let digits = [KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9];
+    digits
+        .iter()
+        .position(|digit| digit == key)
+        .and_then(|position| u8::try_from(position).ok())
+}
+
+
+fn describe(tag: GimmickTag, properties: &GimmickProperties) -> String {
+    let mut line = format!("{tag:?}");
+    if rotates(tag) {
+        line += &format!("  facing {:?} (R)", properties.rotation.unwrap_or(MoveDirection::Up));
+    }
+    if has_channel(tag) {
+        line += &format!("  channel {} (0-9)", properties.channel.unwrap_or_default());
+    }
+    if counts_keys(tag) {
+        line += &format!("  keys {} (Up/Down)", properties.require_keys.unwrap_or(1));
+    }
+    if targets(tag) {
+        line += &match properties.target {
+            Some(target) => format!("  to page {} ({}, {}) (T)", target.page + 1, target.col, target.row),
+            None => "  no target (T)".to_string(),
+        };
+    }
+    line
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::Input;
+    use bevy::prelude::KeyCode;
+
+    use crate::loader::json::{GimmickProperties, PortalTarget};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage_edit::property::{describe, edit};
+
+    fn pressed(key: KeyCode) -> Input<KeyCode> {
+        let mut input = Input::default();
+        input.press(key);
+        input
+    }
+
+
+    #[test]
+    fn rotate_turn_clockwise() {
+        let properties = edit(GimmickTag::Turn, GimmickProperties::default(), &pressed(KeyCode::R));
+        assert_eq!(properties.rotation, Some(MoveDirection::Right));
+
+        let properties = edit(GimmickTag::Turn, properties, &pressed(KeyCode::R));
+        assert_eq!(properties.rotation, Some(MoveDirection::Down));
+    }
+
+
+    #[test]
+    fn face_arrows_and_gates() {
+        let properties = GimmickProperties { rotation: Some(MoveDirection::Left), ..Default::default() };
+
+        assert_eq!(edit(GimmickTag::Arrow, properties, &pressed(KeyCode::R)).rotation, Some(MoveDirection::Up));
+        assert_eq!(describe(GimmickTag::Gate, &properties), "Gate  facing Left (R)");
+    }
+
+
+    #[test]
+    fn only_edit_what_the_gimmick_uses() {
+        assert_eq!(edit(GimmickTag::Rock, GimmickProperties::default(), &pressed(KeyCode::R)), GimmickProperties::default());
+        assert_eq!(edit(GimmickTag::Turn, GimmickProperties::default(), &pressed(KeyCode::Key2)), GimmickProperties::default());
+        assert_eq!(edit(GimmickTag::Key, GimmickProperties::default(), &pressed(KeyCode::Up)), GimmickProperties::default());
+    }
+
+
+    #[test]
+    fn pair_teleporters_by_channel() {
+        let properties = edit(GimmickTag::Teleporter, GimmickProperties::default(), &pressed(KeyCode::Key1));
+        assert_eq!(properties.channel, Some(1));
+        assert_eq!(describe(GimmickTag::Teleporter, &properties), "Teleporter  channel 1 (0-9)");
+    }
+
+
+    #[test]
+    fn show_portal_target() {
+        assert_eq!(describe(GimmickTag::Portal, &GimmickProperties::default()), "Portal  no target (T)");
+
+        let properties = GimmickProperties { target: Some(PortalTarget { page: 1, col: 3, row: 4 }), ..Default::default() };
+        assert_eq!(describe(GimmickTag::Portal, &properties), "Portal  to page 2 (3, 4) (T)");
+    }
+
+
+    #[test]
+    fn lock_channel_and_keys() {
+        let properties = edit(GimmickTag::Lock, GimmickProperties::default(), &pressed(KeyCode::Key3));
+        assert_eq!(properties.channel, Some(3));
+
+        let properties = edit(GimmickTag::Lock, properties, &pressed(KeyCode::Up));
+        assert_eq!(properties.require_keys, Some(2));
+        assert_eq!(describe(GimmickTag::Lock, &properties), "Lock  channel 3 (0-9)  keys 2 (Up/Down)");
+
+        let properties = edit(GimmickTag::Lock, properties, &pressed(KeyCode::Down));
+        let properties = edit(GimmickTag::Lock, properties, &pressed(KeyCode::Down));
+        assert_eq!(properties.require_keys, Some(1));
+    }
+}
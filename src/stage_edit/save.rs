@@ -5,31 +5,39 @@ use bevy::core::Name;
 use bevy::ecs::system::SystemParam;
 use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
 use bevy::input::Input;
-use bevy::math::{I64Vec2, Vec2};
-use bevy::prelude::{ButtonBundle, ChildBuilder, Color, Commands, Component, Condition, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, JustifyContent, MouseButton, NextState, NodeBundle, Query, Res, ResMut, resource_changed, resource_exists_and_equals, TextBundle, Transform, With};
-use bevy::sprite::Sprite;
+use bevy::math::IVec2;
+use bevy::prelude::{ButtonBundle, ChildBuilder, Color, Commands, Component, Condition, Entity, Event, EventReader, EventWriter, in_state, IntoSystemConfigs, JustifyContent, MouseButton, NextState, NodeBundle, Query, Res, ResMut, resource_changed, resource_exists_and_equals, TextBundle, Transform, With, Without};
 use bevy::text::{Text, TextStyle};
 use bevy::ui::{AlignItems, BackgroundColor, Display, FlexDirection, Interaction, PositionType, Style, UiRect, Val};
 use bevy::utils::default;
 use bevy::window::ReceivedCharacter;
+use itertools::Itertools;
 
 use crate::assets::font::FontAssets;
+use crate::error::{GameError, GameResult};
 use crate::extension::InteractionCondition;
 use crate::gama_state::GameState;
-use crate::loader::{StageLoadable, StageLoader};
-use crate::loader::json::{ItemAreaJson, ItemCell, PageJson, StageCell, StageJson};
+use crate::loader::{StageLoadable, StageLoader, validation};
+use crate::loader::validation::StageIssue;
+use crate::loader::json::{GimmickProperties, ItemCell, PageJson, STAGE_VERSION, StageCell, StageJson};
 use crate::page::page_index::PageIndex;
 use crate::page::page_param::PageParams;
+use crate::simulation::board::Board;
+use crate::simulation::solver::Solve;
 use crate::stage::playing::gimmick::{Gimmick, GimmickItem};
 use crate::stage::playing::gimmick::tag::GimmickTag;
-use crate::stage_edit::page::item_area::ItemArea;
-use crate::stage_edit::page::Page;
+use crate::stage::playing::solve_task::SolveTask;
 use crate::stage_edit::StageEditStatus;
 
 #[derive(Event)]
 struct SaveUiDespawnEvent;
 
 
+/// A stage that passed [`validation::checked`], waiting for the solver to find that a goal can be reached.
+#[derive(Component)]
+struct PendingSave(StageJson);
+
+
 #[derive(Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
 pub struct StageEditSavePlugin;
 
@@ -44,6 +52,7 @@ impl Plugin for StageEditSavePlugin {
             .add_systems(Update, (
                 input_key,
                 save_system,
+                save_reachable_stage_system,
                 despawn_ui_event
             )
                 .run_if(in_state(GameState::StageEdit).and_then(resource_exists_and_equals(StageEditStatus::SaveStage))),
@@ -87,6 +96,7 @@ fn setup(
                 cancel_button(parent, &font);
                 save_button(parent, &font);
             });
+            save_error_text(parent, &font);
         });
 }
 
@@ -95,6 +105,28 @@ fn setup(
 struct StageNameText;
 
 
+#[derive(Component, Debug, Default, Eq, PartialEq, Copy, Clone)]
+struct SaveErrorText;
+
+
+fn save_error_text(parent: &mut ChildBuilder, font: &FontAssets) {
+    parent.spawn(TextBundle {
+        text: Text::from_section("", TextStyle {
+            font: font.button_text.clone(),
+            font_size: 24.,
+            color: Color::MAROON,
+        }),
+        style: Style {
+            max_width: Val::Px(600.),
+            margin: UiRect::top(Val::Px(16.)),
+            ..default()
+        },
+        ..default()
+    })
+        .insert((SaveErrorText, Name::new("SaveErrorText")));
+}
+
+
 fn stage_name_text(parent: &mut ChildBuilder, font: &FontAssets) {
     parent.spawn(NodeBundle {
         style: Style {
@@ -188,25 +220,60 @@ struct ButtonsParams<'w, 's> {
 
 #[derive(SystemParam)]
 struct SaveParams<'w, 's> {
+    commands: Commands<'w, 's>,
     state: ResMut<'w, NextState<GameState>>,
     despawn_writer: EventWriter<'w, SaveUiDespawnEvent>,
     page_params: PageParams<'w>,
-    pages: Query<'w, 's, (&'static Transform, &'static PageIndex), With<Page>>,
-    item_area: Query<'w, 's, (&'static Sprite, &'static PageIndex), With<ItemArea>>,
+    source: Res<'w, StageJson>,
+    loader: Res<'w, StageLoader>,
     stage_name: Query<'w, 's, &'static mut Text, With<StageNameText>>,
-    stage_items: Query<'w, 's, (&'static Transform, &'static GimmickItem, &'static PageIndex)>,
-    stage_cells: Query<'w, 's, (&'static Transform, &'static GimmickTag, &'static PageIndex), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
+    error_text: Query<'w, 's, &'static mut Text, (With<SaveErrorText>, Without<StageNameText>)>,
+    stage_items: Query<'w, 's, (&'static Transform, &'static GimmickItem, &'static PageIndex, Option<&'static GimmickProperties>)>,
+    stage_cells: Query<'w, 's, (&'static Transform, &'static GimmickTag, &'static PageIndex, Option<&'static GimmickProperties>), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
+    pending: Query<'w, 's, (), With<PendingSave>>,
 }
 
 
 impl<'w, 's> SaveParams<'w, 's> {
-    #[inline]
     fn save_stage(&mut self) {
+        if !self.pending.is_empty() {
+            return;
+        }
         let stage_name = self.stage_name.single().sections[0].value.clone();
-        save_stage(stage_name, &self.page_params, &self.pages, &self.item_area, &self.stage_items, &self.stage_cells);
+        let stage = create_stage(stage_name, self.source.hints, &self.page_params, &self.stage_items, &self.stage_cells);
+
+        match validation::checked(stage).and_then(|stage| Ok((Board::new(&stage)?, stage))) {
+            Ok((board, stage)) => {
+                let state = board.initial_state();
+                self.commands.spawn((PendingSave(stage), SolveTask::new(board, state)));
+                self.error_text.single_mut().sections[0].value = "Checking that the goal can be reached...".to_string();
+            }
+            Err(error) => {
+                self.error_text.single_mut().sections[0].value = error.to_string();
+            }
+        }
+    }
+
 
-        self.state.set(GameState::Title);
-        self.despawn_writer.send(SaveUiDespawnEvent);
+    /// Stages too large for the solver are given the benefit of the doubt.
+    fn finish_save(&mut self, stage: StageJson, solve: Solve) {
+        let saved: GameResult = match solve {
+            Solve::Unsolvable => Err(GameError::InvalidStage {
+                name: stage.name,
+                issues: vec![StageIssue::UnreachableGoal],
+            }),
+            Solve::Solved(_) | Solve::GaveUp => store(&*self.loader, &StageLoader::built_in(), &self.source.name, &stage),
+        };
+
+        match saved {
+            Ok(()) => {
+                self.state.set(GameState::Title);
+                self.despawn_writer.send(SaveUiDespawnEvent);
+            }
+            Err(error) => {
+                self.error_text.single_mut().sections[0].value = error.to_string();
+            }
+        }
     }
 }
 
@@ -240,11 +307,27 @@ fn despawn_ui_event(
     mut reader: EventReader<SaveUiDespawnEvent>,
     mut commands: Commands,
     root: Query<Entity, With<SaveUiRootNode>>,
+    pending: Query<Entity, With<PendingSave>>,
 ) {
     for _ in reader.iter() {
         if let Some(root) = root.iter().next() {
             commands.entity(root).despawn_recursive();
         }
+        for save in pending.iter() {
+            commands.entity(save).despawn();
+        }
+    }
+}
+
+
+fn save_reachable_stage_system(
+    mut save_params: SaveParams,
+    mut saves: Query<(Entity, &PendingSave, &mut SolveTask)>,
+) {
+    for (entity, PendingSave(stage), mut task) in saves.iter_mut() {
+        let Some(solve) = task.poll() else { continue; };
+        save_params.commands.entity(entity).despawn();
+        save_params.finish_save(stage.clone(), solve);
     }
 }
 
@@ -286,92 +369,200 @@ fn input_key(
 }
 
 
-fn save_stage(
+/// Saving an edited stage under another name renames it rather than leaving a copy behind,
+/// but never overwrites another stage or one that is read only, built-in stages included.
+fn store(loader: &impl StageLoadable, built_in: &impl StageLoadable, original: &str, stage: &StageJson) -> GameResult {
+    let stored = loader.list()?;
+    // The assets directory can't be read on the web, so built-in names are only checked where it can.
+    let built_in = built_in.list().unwrap_or_default();
+    if stored.iter().chain(built_in.iter()).any(|metadata| metadata.name == stage.name && metadata.read_only) {
+        return Err(GameError::ReadOnlyStages);
+    }
+    if stage.name != original {
+        if stored.iter().any(|metadata| metadata.name == stage.name) {
+            return Err(GameError::StageExists(stage.name.clone()));
+        }
+        if stored.iter().any(|metadata| metadata.name == original) {
+            loader.rename(original, &stage.name)?;
+        }
+    }
+    loader.save(stage)
+}
+
+
+/// `hints` is kept from the stage being edited, since the editor has no way to change it.
+fn create_stage(
     stage_name: String,
+    hints: Option<usize>,
     page_params: &PageParams,
-    pages: &Query<(&Transform, &PageIndex), With<Page>>,
-    item_area: &Query<(&Sprite, &PageIndex), With<ItemArea>>,
-    stage_items: &Query<(&Transform, &GimmickItem, &PageIndex)>,
-    stage_cells: &Query<(&Transform, &GimmickTag, &PageIndex), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
-) {
+    stage_items: &Query<(&Transform, &GimmickItem, &PageIndex, Option<&GimmickProperties>)>,
+    stage_cells: &Query<(&Transform, &GimmickTag, &PageIndex, Option<&GimmickProperties>), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
+) -> StageJson {
     let pages = (0..page_params.page_count())
-        .map(|page_index| create_page_asset(page_index, pages, item_area, stage_items, stage_cells))
+        .map(|page_index| create_page_asset(page_index, stage_items, stage_cells))
         .collect::<Vec<PageJson>>();
 
-    let json = StageJson {
+    StageJson {
+        version: STAGE_VERSION,
         name: stage_name,
         pages,
-    };
-    StageLoader::new().save(&json).unwrap();
+        hints,
+    }
 }
 
 
 fn create_page_asset(
     page_index: usize,
-    pages: &Query<(&Transform, &PageIndex), With<Page>>,
-    item_area: &Query<(&Sprite, &PageIndex), With<ItemArea>>,
-    stage_items: &Query<(&Transform, &GimmickItem, &PageIndex)>,
-    stage_cells: &Query<(&Transform, &GimmickTag, &PageIndex), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
+    stage_items: &Query<(&Transform, &GimmickItem, &PageIndex, Option<&GimmickProperties>)>,
+    stage_cells: &Query<(&Transform, &GimmickTag, &PageIndex, Option<&GimmickProperties>), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
 ) -> PageJson {
-    let mut cells = Vec::new();
-
-    for (pos, tags) in cells_in_page(page_index, stage_cells) {
-        cells.push(StageCell::new(Vec2::new(pos.x as f32, pos.y as f32), tags));
-    }
-
-    let (page_transform, _) = pages
-        .iter()
-        .find(|(_, idx)| ***idx == page_index)
-        .unwrap();
+    let cells = cells_in_page(page_index, stage_cells)
+        .into_values()
+        .sorted_by_key(|cell| (cell.row, cell.col))
+        .collect::<Vec<StageCell>>();
 
-    let (item_area_sprite, _) = item_area
+    let items = stage_items
         .iter()
-        .find(|(_, idx)| ***idx == page_index)
-        .unwrap();
-
-    let tags = stage_items
-        .iter()
-        .filter(|(_, _, idx)| ***idx == page_index)
-        .map(|(st, tag, _)| ItemCell {
-            x: st.translation.x,
-            y: st.translation.y,
+        .filter(|(_, _, idx, _)| ***idx == page_index)
+        .sorted_by(|(st1, ..), (st2, ..)| st2.translation.y.total_cmp(&st1.translation.y))
+        .map(|(st, tag, _, properties)| ItemCell {
+            row: ItemCell::row_from_pixel(st.translation.y),
             tag: tag.0,
+            properties: properties.copied().unwrap_or_default(),
         })
         .collect::<Vec<ItemCell>>();
 
     PageJson {
-        x: page_transform.translation.x,
-        y: page_transform.translation.y,
         cells,
-        item_area: ItemAreaJson {
-            width: item_area_sprite.custom_size.unwrap().x,
-            height: item_area_sprite.custom_size.unwrap().y,
-            tags,
-        },
+        items,
     }
 }
 
 
 fn cells_in_page(
     page_index: usize,
-    stage_cells: &Query<(&Transform, &GimmickTag, &PageIndex), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
-) -> HashMap<I64Vec2, Vec<GimmickTag>> {
-    let mut stage = HashMap::<I64Vec2, Vec<GimmickTag>>::new();
+    stage_cells: &Query<(&Transform, &GimmickTag, &PageIndex, Option<&GimmickProperties>), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
+) -> HashMap<IVec2, StageCell> {
+    let mut stage = HashMap::<IVec2, StageCell>::new();
 
     stage_cells
         .iter()
-        .filter(|(_, _, idx)| ***idx == page_index)
-        .for_each(|(transform, gimmick, _)| {
-            let key = transform.translation.truncate().as_i64vec2();
-            if let std::collections::hash_map::Entry::Vacant(e) = stage.entry(key) {
-                e.insert(vec![*gimmick]);
-            } else {
-                stage
-                    .get_mut(&key)
-                    .unwrap()
-                    .push(*gimmick);
+        .filter(|(_, _, idx, _)| ***idx == page_index)
+        .for_each(|(transform, gimmick, _, properties)| {
+            let cell = StageCell::from_pixel(transform.translation.truncate(), Vec::new());
+            let cell = stage
+                .entry(IVec2::new(i32::from(cell.col), i32::from(cell.row)))
+                .or_insert(cell);
+
+            cell.tags.push(*gimmick);
+            if let Some(properties) = properties.filter(|properties| !properties.is_empty()) {
+                cell.properties.insert(*gimmick, *properties);
             }
         });
 
     stage
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bevy::app::Startup;
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::{Query, Transform, With};
+
+    use crate::error::GameError;
+    use crate::loader::json::{GimmickProperties, StageJson};
+    use crate::loader::memory::MemoryStageLoader;
+    use crate::loader::{StageLoadable, StageLoader};
+    use crate::page::page_count::PageCount;
+    use crate::page::page_index::PageIndex;
+    use crate::page::page_param::PageParams;
+    use crate::simulation::tests::{page, rotate, stage};
+    use crate::stage::playing::gimmick::{Gimmick, GimmickItem};
+    use crate::stage::playing::gimmick::tag::GimmickTag;
+    use crate::stage::playing::move_direction::MoveDirection;
+    use crate::stage_edit::save::{create_stage, store};
+    use crate::stage_edit::setup;
+    use crate::stage_edit::tests::new_stage_edit_app;
+
+    fn named(name: &str) -> StageJson {
+        let mut stage = stage(vec![page(&[
+            "#####",
+            "#P.G#",
+            "#####",
+        ], &[])]);
+        stage.name = name.to_string();
+        stage
+    }
+
+
+    #[test]
+    fn save_under_new_name_renames() {
+        let loader = MemoryStageLoader::default();
+        loader.save(&named("old")).unwrap();
+
+        store(&loader, &MemoryStageLoader::default(), "old", &named("new")).unwrap();
+        let names = loader.list().unwrap().into_iter().map(|metadata| metadata.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["new".to_string()]);
+    }
+
+
+    #[test]
+    fn never_overwrite_another_stage() {
+        let loader = MemoryStageLoader::default();
+        loader.save(&named("mine")).unwrap();
+        loader.save(&named("other")).unwrap();
+
+        let built_in = MemoryStageLoader::default();
+        assert!(matches!(store(&loader, &built_in, "mine", &named("other")), Err(GameError::StageExists(name)) if name == "other"));
+        store(&loader, &built_in, "mine", &named("mine")).unwrap();
+        store(&loader, &built_in, "", &named("fresh")).unwrap();
+        assert_eq!(loader.list().unwrap().len(), 3);
+    }
+
+
+    #[test]
+    fn never_save_over_built_in_stage() {
+        let loader = MemoryStageLoader::default();
+
+        assert!(matches!(store(&loader, &StageLoader::built_in(), "", &named("stage1")), Err(GameError::ReadOnlyStages)));
+        assert!(loader.list().unwrap().is_empty());
+    }
+
+
+    #[test]
+    fn keep_loaded_properties() {
+        let mut page = page(&[
+            "######",
+            "#PTKL#",
+            "######",
+        ], &[GimmickTag::Key]);
+        rotate(&mut page, (2, 1), MoveDirection::Left);
+        page.cells[9].properties.insert(GimmickTag::Key, GimmickProperties { channel: Some(2), ..Default::default() });
+        page.cells[10].properties.insert(GimmickTag::Lock, GimmickProperties { channel: Some(2), require_keys: Some(2), ..Default::default() });
+        page.items[0].properties.channel = Some(2);
+        let source = stage(vec![page]);
+        let mut app = new_stage_edit_app(PageCount::new(1));
+        app.insert_resource(source.clone());
+        app.add_systems(Startup, setup);
+        app.update();
+
+        let mut system_state = SystemState::<(
+            PageParams,
+            Query<(&Transform, &GimmickItem, &PageIndex, Option<&GimmickProperties>)>,
+            Query<(&Transform, &GimmickTag, &PageIndex, Option<&GimmickProperties>), (With<Transform>, With<Gimmick>, With<PageIndex>)>,
+        )>::new(&mut app.world);
+        let (page_params, items, cells) = system_state.get_mut(&mut app.world);
+        let saved = create_stage(source.name.clone(), source.hints, &page_params, &items, &cells);
+
+        let properties = |stage: &StageJson| stage.pages[0]
+            .cells
+            .iter()
+            .map(|cell| ((cell.col, cell.row), cell.properties.clone()))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(properties(&saved), properties(&source));
+        assert_eq!(saved.pages[0].items, source.pages[0].items);
+    }
+}
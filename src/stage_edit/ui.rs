@@ -66,7 +66,12 @@ fn footer(parent: &mut ChildBuilder, asset: &GimmickAssets, edit_assets: &StageE
                 GimmickTag::IceBox,
                 GimmickTag::Turn,
                 GimmickTag::Key,
-                GimmickTag::Lock
+                GimmickTag::Lock,
+                GimmickTag::Hole,
+                GimmickTag::Teleporter,
+                GimmickTag::Portal,
+                GimmickTag::Arrow,
+                GimmickTag::Gate
             ]);
 
             spawn_eraser(parent, edit_assets);
@@ -1,10 +1,16 @@
-use bevy::app::{App, Plugin, Update};
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use bevy::app::{App, Plugin, PostUpdate, Update};
 use bevy::asset::AssetServer;
 use bevy::core::Name;
-use bevy::hierarchy::BuildChildren;
-use bevy::prelude::{AlignItems, AudioBundle, ButtonBundle, ChildBuilder, Color, Commands, Event, EventReader, in_state, Input, IntoSystemConfigs, JustifyContent, KeyCode, NextState, NodeBundle, OnEnter, OnExit, PlaybackSettings, Query, RepeatedGridTrack, Res, ResMut, Text, TextBundle, TextStyle, Val, With};
+use bevy::ecs::change_detection::{DetectChanges, DetectChangesMut};
+use bevy::hierarchy::{BuildChildren, DespawnRecursiveExt};
+use bevy::prelude::{AlignItems, AudioBundle, ButtonBundle, ChildBuilder, Color, Commands, Entity, Event, EventReader, FlexDirection, in_state, Input, IntoSystemConfigs, JustifyContent, KeyCode, NextState, NodeBundle, OnEnter, OnExit, Overflow, PlaybackSettings, PositionType, Query, RepeatedGridTrack, Res, ResMut, Resource, Text, TextBundle, TextSection, TextStyle, UiRect, Val, With};
 use bevy::ui::{BackgroundColor, Display, Interaction, Style};
 use bevy::utils::default;
+use bevy::window::FileDragAndDrop;
 use bevy_input_sequence::AddInputSequenceEvent;
 use bevy_input_sequence::prelude::{InputSequence, Timeout};
 use bevy_trait_query::imports::Component;
@@ -12,10 +18,14 @@ use bevy_trait_query::imports::Component;
 use crate::assets::font::FontAssets;
 use crate::assets::stage::BuiltInStages;
 use crate::destroy_all;
+use crate::error::{GameError, GameResult};
 use crate::extension::InteractionCondition;
 use crate::gama_state::GameState;
 use crate::loader::json::StageJson;
+use crate::loader::{StageLoadable, StageLoader, StageLoadError, StageLoadResult};
 use crate::page::page_count::PageCount;
+use crate::progress::{Progress, StageId};
+use crate::stage::replay::{ReplayJson, ReplayPlayback};
 
 #[derive(Default, Debug, PartialEq, Copy, Clone)]
 pub struct StageSelectPlugin;
@@ -25,13 +35,18 @@ impl Plugin for StageSelectPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_input_sequence_event::<SecretCommandEvent>()
+            .init_resource::<SelectedPack>()
             .add_systems(OnEnter(GameState::StageSelect), setup)
             .add_systems(OnExit(GameState::StageSelect), destroy_all)
             .add_systems(Update, (
+                select_pack,
                 select_stage,
+                delete_user_stage_system,
+                replay_file_drop_system,
                 back_scene_system,
                 before_stage_edit_system
-            ).run_if(in_state(GameState::StageSelect)));
+            ).run_if(in_state(GameState::StageSelect)))
+            .add_systems(PostUpdate, spawn_stage_panel_system.run_if(in_state(GameState::StageSelect)));
     }
 }
 
@@ -40,10 +55,38 @@ impl Plugin for StageSelectPlugin {
 struct SecretCommandEvent;
 
 
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+enum PackChoice {
+    BuiltIn(usize),
+    User,
+}
+
+
+/// The pack whose stages are shown; `None` shows the pack chooser. Kept when coming back from a stage.
+#[derive(Resource, Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+struct SelectedPack(Option<PackChoice>);
+
+
+#[derive(Component, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+struct PackButton(Option<PackChoice>);
+
+
+#[derive(Component, Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+struct StageSelectScreen;
+
+
+#[derive(Component, Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+struct StagePanel;
+
+
+/// Shows why a dropped replay couldn't be played or a stage couldn't be deleted.
+#[derive(Component, Default, Debug, Copy, Clone, Hash, Eq, PartialEq)]
+struct ErrorText;
+
+
 fn setup(
     mut commands: Commands,
     font: Res<FontAssets>,
-    stages: Res<BuiltInStages>,
     asset_server: Res<AssetServer>,
 ) {
     commands.spawn(AudioBundle {
@@ -78,8 +121,69 @@ fn setup(
         },
         ..default()
     })
-        .insert(Name::new("Screen"))
-        .with_children(|parent| { spawn_stage_panel(parent, &font, &stages); });
+        .insert((Name::new("Screen"), StageSelectScreen));
+
+    commands.spawn(TextBundle::from_section("", TextStyle {
+        font: font.button_text.clone(),
+        font_size: 23.,
+        color: Color::ORANGE_RED,
+    }).with_style(Style {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(10.),
+        left: Val::Px(10.),
+        ..default()
+    }))
+        .insert((Name::new("ErrorText"), ErrorText));
+}
+
+
+/// Dropping a replay file plays it back on its stage.
+fn replay_file_drop_system(
+    mut state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    mut evr: EventReader<FileDragAndDrop>,
+    mut error_text: Query<&mut Text, With<ErrorText>>,
+    stages: Res<BuiltInStages>,
+    loader: Res<StageLoader>,
+) {
+    for ev in evr.iter() {
+        if let FileDragAndDrop::DroppedFile { window: _, path_buf } = ev {
+            match read_dropped_replay(path_buf, &stages, &loader) {
+                Ok((stage_id, stage, replay)) => {
+                    commands.insert_resource(stage_id);
+                    commands.insert_resource(PageCount::new(stage.pages.len()));
+                    commands.insert_resource(stage);
+                    commands.insert_resource(ReplayPlayback::new(replay));
+                    state.set(GameState::Stage);
+                }
+                Err(error) => {
+                    error_text.single_mut().sections[0].value = error.to_string();
+                }
+            }
+        }
+    }
+}
+
+
+fn read_dropped_replay(path: &Path, stages: &BuiltInStages, loader: &StageLoader) -> GameResult<(StageId, StageJson, ReplayJson)> {
+    let replay = serde_json::from_str::<ReplayJson>(&fs::read_to_string(path)?)?;
+    let (stage_id, stage) = stages
+        .0
+        .iter()
+        .flat_map(|pack| pack.stages.iter())
+        .find(|stage| stage.stage.name == replay.stage_name)
+        .map(|stage| (stage.stage_id(), stage.stage.clone()))
+        .or_else(|| loader
+            .load()
+            .ok()?
+            .into_iter()
+            .flatten()
+            .find(|stage| stage.name == replay.stage_name)
+            .map(|stage| (StageId::user(&stage.name), stage)))
+        .ok_or_else(|| GameError::ReplayStageNotFound(replay.stage_name.clone()))?;
+
+    replay.check_stage(&stage)?;
+    Ok((stage_id, stage, replay))
 }
 
 
@@ -93,22 +197,198 @@ fn before_stage_edit_system(
 }
 
 
-fn spawn_stage_panel(parent: &mut ChildBuilder, font: &FontAssets, stages: &BuiltInStages) {
-    parent.spawn(NodeBundle {
+/// Rebuilds the panel whenever another pack is chosen.
+fn spawn_stage_panel_system(
+    mut commands: Commands,
+    screen: Query<Entity, With<StageSelectScreen>>,
+    panel: Query<Entity, With<StagePanel>>,
+    selected: Res<SelectedPack>,
+    font: Res<FontAssets>,
+    stages: Res<BuiltInStages>,
+    progress: Res<Progress>,
+    loader: Res<StageLoader>,
+) {
+    if !panel.is_empty() && !selected.is_changed() {
+        return;
+    }
+    let Ok(screen) = screen.get_single() else { return; };
+    for panel in panel.iter() {
+        commands.entity(panel).despawn_recursive();
+    }
+
+    commands.entity(screen).with_children(|parent| {
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(80.),
+                height: Val::Percent(80.),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            ..default()
+        })
+            .insert((Name::new("StagePanel"), StagePanel))
+            .with_children(|parent| {
+                match selected.0 {
+                    Some(PackChoice::BuiltIn(pack_index)) if stages.is_unlocked(pack_index, &progress) => {
+                        let pack = &stages.0[pack_index];
+                        spawn_section_title(parent, &font, &pack.name);
+                        spawn_stage_grid(parent, pack.id.clone(), |parent| {
+                            for stage in pack.stages.iter() {
+                                spawn_stage_button(parent, &font, &stage.name, stage.stage_id(), &stage.stage, &progress);
+                            }
+                        });
+                        spawn_pack_back_button(parent, &font);
+                    }
+                    Some(PackChoice::User) => {
+                        spawn_section_title(parent, &font, "My Stages");
+                        spawn_user_stages(parent, &font, loader.load(), &progress);
+                        spawn_pack_back_button(parent, &font);
+                    }
+                    _ => {
+                        spawn_section_title(parent, &font, "Packs");
+                        spawn_stage_grid(parent, "Packs", |parent| {
+                            for (pack_index, pack) in stages.0.iter().enumerate() {
+                                let unlocked = stages.is_unlocked(pack_index, &progress);
+                                let detail = if unlocked {
+                                    format!("{}/{}", pack.cleared_count(&progress), pack.stages.len())
+                                } else {
+                                    stages.requirement(pack_index)
+                                };
+                                spawn_pack_button(parent, &font, &pack.name, &detail, unlocked.then_some(PackChoice::BuiltIn(pack_index)));
+                            }
+                            spawn_pack_button(parent, &font, "My Stages", "", Some(PackChoice::User));
+                        });
+                    }
+                }
+            });
+    });
+}
+
+
+fn spawn_user_stages(
+    parent: &mut ChildBuilder,
+    font: &FontAssets,
+    user_stages: GameResult<Vec<StageLoadResult>>,
+    progress: &Progress,
+) {
+    match user_stages {
+        Ok(user_stages) => spawn_stage_grid(parent, "UserStages", |parent| {
+            for stage in user_stages.iter() {
+                match stage {
+                    Ok(stage) => spawn_stage_button(parent, font, &stage.name, StageId::user(&stage.name), stage, progress),
+                    Err(error) => spawn_broken_stage(parent, font, error)
+                }
+            }
+        }),
+        Err(error) => {
+            parent.spawn(TextBundle::from_section(error.to_string(), TextStyle {
+                font: font.button_text.clone(),
+                font_size: 16.,
+                color: Color::MAROON,
+            }));
+        }
+    }
+}
+
+
+/// Locked packs are passed without a choice and can't be pressed.
+fn spawn_pack_button(parent: &mut ChildBuilder, font: &FontAssets, name: &str, detail: &str, choice: Option<PackChoice>) {
+    let background = if choice.is_some() {
+        Color::from([80. / 255., 150. / 255., 250. / 255., 0.8])
+    } else {
+        Color::from([0.5, 0.5, 0.5, 0.5])
+    };
+
+    let mut button = parent.spawn(ButtonBundle {
         style: Style {
-            width: Val::Percent(80.),
-            height: Val::Percent(80.),
-            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            ..stage_entry_style()
+        },
+        background_color: BackgroundColor::from(background),
+        ..default()
+    });
+    button
+        .insert(Name::new(name.to_string()))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_sections([
+                TextSection::new(name, TextStyle {
+                    font: font.button_text.clone(),
+                    font_size: 30.,
+                    color: Color::BLACK,
+                }),
+                TextSection::new(format!("\n{detail}"), TextStyle {
+                    font: font.button_text.clone(),
+                    font_size: 16.,
+                    color: Color::DARK_GRAY,
+                }),
+            ]));
+        });
+    if choice.is_some() {
+        button.insert(PackButton(choice));
+    }
+}
+
+
+fn spawn_pack_back_button(parent: &mut ChildBuilder, font: &FontAssets) {
+    parent.spawn(ButtonBundle {
+        style: Style {
+            width: Val::Px(120.),
+            height: Val::Px(40.),
             justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: BackgroundColor::from(Color::from([0.5, 0.5, 0.5, 0.8])),
+        ..default()
+    })
+        .insert((Name::new("PackBackButton"), PackButton(None)))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Back", TextStyle {
+                font: font.button_text.clone(),
+                font_size: 24.,
+                color: Color::BLACK,
+            }));
+        });
+}
+
+
+fn select_pack(
+    mut selected: ResMut<SelectedPack>,
+    buttons: Query<(&Interaction, &PackButton)>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if interaction.pressed() {
+            selected.set_if_neq(SelectedPack(button.0));
+            return;
+        }
+    }
+}
+
+
+fn spawn_section_title(parent: &mut ChildBuilder, font: &FontAssets, title: &str) {
+    parent.spawn(TextBundle::from_section(title, TextStyle {
+        font: font.button_text.clone(),
+        font_size: 36.,
+        color: Color::WHITE,
+    }));
+}
+
+
+fn spawn_stage_grid(parent: &mut ChildBuilder, name: impl Into<Cow<'static, str>>, spawn_entries: impl FnOnce(&mut ChildBuilder)) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Percent(100.),
             grid_template_columns: RepeatedGridTrack::flex(4, 1.0),
             column_gap: Val::Px(12.0),
+            row_gap: Val::Px(12.0),
             display: Display::Grid,
             ..default()
         },
         ..default()
     })
-        .insert(Name::new("StagePanel"))
-        .with_children(|parent| { spawn_stages(parent, font, stages); });
+        .insert(Name::new(name))
+        .with_children(spawn_entries);
 }
 
 
@@ -116,41 +396,118 @@ fn spawn_stage_panel(parent: &mut ChildBuilder, font: &FontAssets, stages: &Buil
 struct StagePlayButton;
 
 
-fn spawn_stages(parent: &mut ChildBuilder, font: &FontAssets, stages: &BuiltInStages) {
-    for stage in stages.0.iter() {
-        parent.spawn(ButtonBundle {
-            style: Style {
-                width: Val::Px(200.),
-                height: Val::Px(100.),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
+/// Cleared stages get a badge and their best move count.
+fn spawn_stage_button(parent: &mut ChildBuilder, font: &FontAssets, label: &str, stage_id: StageId, stage: &StageJson, progress: &Progress) {
+    let record = progress.record(&stage_id);
+    parent.spawn(ButtonBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            ..stage_entry_style()
+        },
+        background_color: BackgroundColor::from(Color::from([80. / 255., 150. / 255., 250. / 255., 0.8])),
+        ..default()
+    })
+        .insert((
+            Name::new(stage.name.clone()),
+            stage_id,
+            stage.clone(),
+            StagePlayButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle {
+                text: Text::from_section(
+                    label,
+                    TextStyle {
+                        font: font.button_text.clone(),
+                        font_size: 30.,
+                        color: Color::BLACK,
+                    },
+                ),
+                style: Style {
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
                 ..default()
-            },
-            background_color: BackgroundColor::from(Color::from([80. / 255., 150. / 255., 250. / 255., 0.8])),
-            ..default()
-        })
-            .insert((
-                Name::new(stage.name.clone()),
-                stage.clone(),
-                StagePlayButton,
-            ))
-            .with_children(|parent| {
-                parent.spawn(TextBundle {
-                    text: Text::from_section(
-                        stage.name.clone(),
+            });
+
+            let Some(record) = record else { return; };
+            parent.spawn(TextBundle::from_section(
+                format!("Best: {} moves", record.best_moves),
+                TextStyle {
+                    font: font.button_text.clone(),
+                    font_size: 16.,
+                    color: Color::BLACK,
+                },
+            ));
+            parent.spawn(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(4.),
+                    right: Val::Px(4.),
+                    padding: UiRect::horizontal(Val::Px(4.)),
+                    ..default()
+                },
+                background_color: BackgroundColor::from(Color::GOLD),
+                ..default()
+            })
+                .insert(Name::new("ClearedBadge"))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "CLEAR",
                         TextStyle {
                             font: font.button_text.clone(),
-                            font_size: 30.,
+                            font_size: 14.,
                             color: Color::BLACK,
                         },
-                    ),
-                    style: Style {
-                        justify_content: JustifyContent::Center,
-                        ..default()
-                    },
-                    ..default()
+                    ));
                 });
-            });
+        });
+}
+
+
+/// A disabled entry showing why the file couldn't be loaded.
+fn spawn_broken_stage(parent: &mut ChildBuilder, font: &FontAssets, error: &StageLoadError) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            overflow: Overflow::clip(),
+            ..stage_entry_style()
+        },
+        background_color: BackgroundColor::from(Color::from([0.5, 0.5, 0.5, 0.5])),
+        ..default()
+    })
+        .insert(Name::new(error.file_name()))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_sections([
+                TextSection::new(
+                    format!("{}\n", error.file_name()),
+                    TextStyle {
+                        font: font.button_text.clone(),
+                        font_size: 20.,
+                        color: Color::DARK_GRAY,
+                    },
+                ),
+                TextSection::new(
+                    error.to_string(),
+                    TextStyle {
+                        font: font.button_text.clone(),
+                        font_size: 12.,
+                        color: Color::MAROON,
+                    },
+                ),
+            ]));
+        });
+}
+
+
+fn stage_entry_style() -> Style {
+    Style {
+        width: Val::Px(200.),
+        height: Val::Px(100.),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
     }
 }
 
@@ -158,14 +515,15 @@ fn spawn_stages(parent: &mut ChildBuilder, font: &FontAssets, stages: &BuiltInSt
 fn select_stage(
     mut state: ResMut<NextState<GameState>>,
     mut commands: Commands,
-    stage_buttons: Query<(&Interaction, &StageJson), (
+    stage_buttons: Query<(&Interaction, &StageId, &StageJson), (
         With<Interaction>,
         With<StageJson>,
         With<StagePlayButton>,
     )>,
 ) {
-    for (interaction, stage_json) in stage_buttons.iter() {
+    for (interaction, stage_id, stage_json) in stage_buttons.iter() {
         if interaction.pressed() {
+            commands.insert_resource(stage_id.clone());
             commands.insert_resource(stage_json.clone());
             commands.insert_resource(PageCount::new(stage_json.pages.len()));
             state.set(GameState::Stage);
@@ -175,11 +533,39 @@ fn select_stage(
 }
 
 
+/// Pressing delete over one of "My Stages" removes it for good.
+fn delete_user_stage_system(
+    mut selected: ResMut<SelectedPack>,
+    mut error_text: Query<&mut Text, With<ErrorText>>,
+    key: Res<Input<KeyCode>>,
+    loader: Res<StageLoader>,
+    stage_buttons: Query<(&Interaction, &StageJson), With<StagePlayButton>>,
+) {
+    if selected.0 != Some(PackChoice::User) || !key.just_pressed(KeyCode::Delete) {
+        return;
+    }
+    let Some((_, stage)) = stage_buttons.iter().find(|(interaction, _)| interaction.hovered()) else { return; };
+
+    match loader.delete(&stage.name) {
+        // Rebuilds the panel without the stage.
+        Ok(()) => selected.set_changed(),
+        Err(error) => error_text.single_mut().sections[0].value = error.to_string(),
+    }
+}
+
+
 fn back_scene_system(
     mut state: ResMut<NextState<GameState>>,
+    mut selected: ResMut<SelectedPack>,
     key: Res<Input<KeyCode>>,
 ) {
-    if key.just_pressed(KeyCode::Escape) {
+    if !key.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    if selected.0.is_some() {
+        selected.0 = None;
+    } else {
         state.set(GameState::Title);
     }
 }
\ No newline at end of file
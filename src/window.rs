@@ -1,18 +1,7 @@
-use bevy::ecs::system::SystemParam;
 use bevy::math::Vec2;
-use bevy::prelude::Query;
-use bevy::window::Window;
 
-#[derive(SystemParam)]
-pub struct WindowParams<'w, 's> {
-    window: Query<'w, 's, &'static Window>,
-}
+pub const WINDOW_WIDTH: f32 = 1200.;
+pub const WINDOW_HEIGHT: f32 = 800.;
 
-
-impl<'w, 's> WindowParams<'w, 's> {
-    #[inline]
-    pub fn top_left(&self) -> Vec2 {
-        let w = self.window.single();
-        Vec2::new(-w.resolution.width() / 2., w.resolution.height() / 2.)
-    }
-}
\ No newline at end of file
+/// The window is not resizable, so stage pages are laid out against this corner.
+pub const WINDOW_TOP_LEFT: Vec2 = Vec2::new(-WINDOW_WIDTH / 2., WINDOW_HEIGHT / 2.);